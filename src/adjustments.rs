@@ -0,0 +1,299 @@
+use std::path::Path;
+
+use image::{
+    imageops,
+    Rgb,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    colors::{
+        linear_channel_to_srgb,
+        srgb_channel_to_linear
+    },
+    config_file::load_toml_or_json
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImageAdjustmentsError {
+    #[error("IoError, reason={0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("SerdeJsonError, reason={0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("TomlError, reason={0}")]
+    TomlError(#[from] toml::de::Error),
+}
+
+/// Single pre-processing step, neutral values keep image unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ImageAdjustment {
+    /// Brightness change in stops, applied in linear light
+    Exposure {
+        stops: f32
+    },
+    /// Neutral 1.0, more spreads tones away from mid gray
+    Contrast {
+        amount: f32
+    },
+    /// Neutral 1.0, 0.0 gives grayscale
+    Saturation {
+        amount: f32
+    },
+    /// Neutral 1.0, more brightens mid tones
+    Gamma {
+        gamma: f32
+    },
+    /// Adds `amount` times difference from gaussian blur, differences up to `threshold` are skipped
+    UnsharpMask {
+        sigma: f32,
+        amount: f32,
+        threshold: u8
+    },
+    /// Median of square window around each pixel
+    Denoise {
+        radius: u32
+    },
+    /// Stretches tones so `clip_percent` of darkest and brightest values become black and white,
+    /// same stretch for all channels keeps color balance
+    AutoLevels {
+        clip_percent: f32
+    },
+}
+
+/// Steps applied in order before palette selection
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageAdjustments {
+    pub steps: Vec<ImageAdjustment>,
+}
+
+/// Luma weights of sRGB primaries
+const LUMA_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+fn to_channel(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+/// Same mapping for every channel value, computed once per value
+fn map_channels<F>(mut rgb_img: RgbImage, map: F) -> RgbImage
+where
+    F: Fn(u8) -> u8
+{
+    let lookup_table: [u8; 256] = std::array::from_fn(|value| map(value as u8));
+    rgb_img.pixels_mut()
+        .for_each(|pixel| pixel.0 = pixel.0.map(|channel| lookup_table[channel as usize]));
+    rgb_img
+}
+
+fn adjust_saturation(mut rgb_img: RgbImage, amount: f32) -> RgbImage {
+    for pixel in rgb_img.pixels_mut() {
+        let luma = (0..3).map(|idx| pixel.0[idx] as f32 * LUMA_WEIGHTS[idx]).sum::<f32>();
+        pixel.0 = pixel.0.map(|channel| to_channel(luma + (channel as f32 - luma) * amount));
+    }
+    rgb_img
+}
+
+fn unsharp_mask(rgb_img: RgbImage, sigma: f32, amount: f32, threshold: u8) -> RgbImage {
+    let blurred_img = imageops::blur(&rgb_img, sigma);
+    RgbImage::from_fn(rgb_img.width(), rgb_img.height(), |x, y| {
+        let pixel = rgb_img.get_pixel(x, y);
+        let blurred_pixel = blurred_img.get_pixel(x, y);
+        Rgb([0, 1, 2].map(|idx| {
+            let difference = pixel.0[idx] as f32 - blurred_pixel.0[idx] as f32;
+            if difference.abs() > threshold as f32 {
+                to_channel(pixel.0[idx] as f32 + difference * amount)
+            } else {
+                pixel.0[idx]
+            }
+        }))
+    })
+}
+
+/// Window is clamped at image edges
+fn median_filter(rgb_img: RgbImage, radius: u32) -> RgbImage {
+    if radius == 0 {
+        return rgb_img;
+    }
+
+    let (width, height) = rgb_img.dimensions();
+    let mut window = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+    RgbImage::from_fn(width, height, |x, y| {
+        let x_range = x.saturating_sub(radius)..(x + radius + 1).min(width);
+        let y_range = y.saturating_sub(radius)..(y + radius + 1).min(height);
+        Rgb([0, 1, 2].map(|idx| {
+            window.clear();
+            for wy in y_range.clone() {
+                window.extend(x_range.clone().map(|wx| rgb_img.get_pixel(wx, wy).0[idx]));
+            }
+            let middle = window.len() / 2;
+            *window.select_nth_unstable(middle).1
+        }))
+    })
+}
+
+/// First level in `levels` order with more than `clipped_count` values up to it
+fn find_clipped_level<I>(histogram: &[usize; 256], mut levels: I, clipped_count: usize) -> Option<usize>
+where
+    I: Iterator<Item = usize>
+{
+    let mut count = 0;
+    levels.find(|&level| {
+        count += histogram[level];
+        count > clipped_count
+    })
+}
+
+fn auto_levels(rgb_img: RgbImage, clip_percent: f32) -> RgbImage {
+    let mut histogram = [0usize; 256];
+    rgb_img.pixels()
+        .flat_map(|pixel| pixel.0)
+        .for_each(|channel| histogram[channel as usize] += 1);
+
+    let values_count = histogram.iter().sum::<usize>();
+    let clipped_count = (values_count as f32 * clip_percent.clamp(0.0, 50.0) / 100.0) as usize;
+    let low = find_clipped_level(&histogram, 0..256, clipped_count);
+    let high = find_clipped_level(&histogram, (0..256).rev(), clipped_count);
+    let (Some(low), Some(high)) = (low, high) else {
+        return rgb_img;
+    };
+    if high <= low {
+        return rgb_img;
+    }
+
+    let scale = 255.0 / (high - low) as f32;
+    map_channels(rgb_img, |channel| to_channel((channel as f32 - low as f32) * scale))
+}
+
+impl ImageAdjustment {
+    pub fn apply(&self, rgb_img: RgbImage) -> RgbImage {
+        match *self {
+            ImageAdjustment::Exposure { stops } => {
+                let gain = 2.0f64.powf(stops as f64);
+                map_channels(rgb_img, |channel| linear_channel_to_srgb(srgb_channel_to_linear(channel) * gain))
+            },
+            ImageAdjustment::Contrast { amount } => {
+                map_channels(rgb_img, |channel| to_channel((channel as f32 - 127.5) * amount + 127.5))
+            },
+            ImageAdjustment::Saturation { amount } => adjust_saturation(rgb_img, amount),
+            ImageAdjustment::Gamma { gamma } => {
+                let exponent = 1.0 / gamma.max(f32::EPSILON);
+                map_channels(rgb_img, |channel| to_channel((channel as f32 / 255.0).powf(exponent) * 255.0))
+            },
+            ImageAdjustment::UnsharpMask { sigma, amount, threshold } => unsharp_mask(rgb_img, sigma, amount, threshold),
+            ImageAdjustment::Denoise { radius } => median_filter(rgb_img, radius),
+            ImageAdjustment::AutoLevels { clip_percent } => auto_levels(rgb_img, clip_percent),
+        }
+    }
+}
+
+impl ImageAdjustments {
+    /// TOML if file has `.toml` extension, JSON otherwise
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, ImageAdjustmentsError> {
+        load_toml_or_json(path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn apply(&self, rgb_img: RgbImage) -> RgbImage {
+        self.steps.iter()
+            .fold(rgb_img, |rgb_img, adjustment| adjustment.apply(rgb_img))
+    }
+}
+
+#[cfg(test)]
+mod test_adjustments {
+    use image::{
+        Rgb,
+        RgbImage
+    };
+
+    use super::{
+        ImageAdjustment,
+        ImageAdjustments
+    };
+
+    fn gradient_img() -> RgbImage {
+        RgbImage::from_fn(16, 16, |x, y| Rgb([(x * 8 + 60) as u8, (y * 8 + 60) as u8, 120]))
+    }
+
+    #[test]
+    fn test_neutral_adjustments_keep_image() {
+        let img = gradient_img();
+        let adjustments = ImageAdjustments {
+            steps: vec![
+                ImageAdjustment::Exposure { stops: 0.0 },
+                ImageAdjustment::Contrast { amount: 1.0 },
+                ImageAdjustment::Saturation { amount: 1.0 },
+                ImageAdjustment::Gamma { gamma: 1.0 },
+                ImageAdjustment::UnsharpMask { sigma: 1.0, amount: 0.0, threshold: 0 },
+                ImageAdjustment::Denoise { radius: 0 },
+            ]
+        };
+        assert_eq!(adjustments.apply(img.clone()), img);
+    }
+
+    #[test]
+    fn test_tone_adjustments() {
+        let img = RgbImage::from_pixel(1, 1, Rgb([100, 150, 200]));
+
+        let brighter = ImageAdjustment::Exposure { stops: 1.0 }.apply(img.clone());
+        assert!(brighter.get_pixel(0, 0).0[0] > 100);
+        let contrasted = ImageAdjustment::Contrast { amount: 2.0 }.apply(img.clone());
+        assert_eq!(contrasted.get_pixel(0, 0).0, [73, 173, 255]);
+        let gray = ImageAdjustment::Saturation { amount: 0.0 }.apply(img.clone());
+        assert!(gray.get_pixel(0, 0).0.iter().all(|&channel| channel == gray.get_pixel(0, 0).0[0]));
+        let lifted = ImageAdjustment::Gamma { gamma: 2.0 }.apply(img);
+        assert_eq!(lifted.get_pixel(0, 0).0[0], 160);
+    }
+
+    #[test]
+    fn test_auto_levels_stretches_range() {
+        let levels = ImageAdjustment::AutoLevels { clip_percent: 0.0 }.apply(gradient_img());
+        let channels = levels.pixels().flat_map(|pixel| pixel.0).collect::<Vec<_>>();
+        assert_eq!(channels.iter().min(), Some(&0));
+        assert_eq!(channels.iter().max(), Some(&255));
+    }
+
+    #[test]
+    fn test_denoise_and_sharpen() {
+        let mut img = RgbImage::from_pixel(9, 9, Rgb([100, 100, 100]));
+        img.put_pixel(4, 4, Rgb([255, 0, 255]));
+        let denoised = ImageAdjustment::Denoise { radius: 1 }.apply(img);
+        assert!(denoised.pixels().all(|pixel| pixel.0 == [100, 100, 100]));
+
+        let edge_img = RgbImage::from_fn(10, 4, |x, _| if x < 5 { Rgb([80, 80, 80]) } else { Rgb([160, 160, 160]) });
+        let sharpened = ImageAdjustment::UnsharpMask { sigma: 1.0, amount: 1.0, threshold: 2 }.apply(edge_img);
+        assert!(sharpened.get_pixel(4, 1).0[0] < 80);
+        assert!(sharpened.get_pixel(5, 1).0[0] > 160);
+        assert_eq!(sharpened.get_pixel(0, 1).0[0], 80);
+    }
+
+    #[test]
+    fn test_toml_steps_order() {
+        let adjustments: ImageAdjustments = toml::from_str(r#"
+            [[steps]]
+            op = "auto_levels"
+            clip_percent = 0.5
+
+            [[steps]]
+            op = "unsharp_mask"
+            sigma = 1.2
+            amount = 0.8
+            threshold = 3
+        "#).unwrap();
+
+        assert_eq!(adjustments.steps, vec![
+            ImageAdjustment::AutoLevels { clip_percent: 0.5 },
+            ImageAdjustment::UnsharpMask { sigma: 1.2, amount: 0.8, threshold: 3 },
+        ]);
+    }
+}
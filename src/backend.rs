@@ -0,0 +1,155 @@
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use image::Rgb;
+
+/// Font of drawn text, DejaVu faces are embedded in PDF and rasterized in PNG,
+/// so every output uses the same glyphs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternFont {
+    Sans,
+    /// Also covers greek and geometric symbols
+    SansBold,
+    Mono,
+    MonoBold,
+    Serif,
+    SerifBold,
+}
+
+impl PatternFont {
+    pub const ALL: [PatternFont; 6] = [
+        PatternFont::Sans,
+        PatternFont::SansBold,
+        PatternFont::Mono,
+        PatternFont::MonoBold,
+        PatternFont::Serif,
+        PatternFont::SerifBold,
+    ];
+
+    /// TrueType font file
+    pub fn get_data(&self) -> &'static [u8] {
+        match self {
+            PatternFont::Sans => dejavu::sans::regular(),
+            PatternFont::SansBold => dejavu::sans::bold(),
+            PatternFont::Mono => dejavu::sans_mono::regular(),
+            PatternFont::MonoBold => dejavu::sans_mono::bold(),
+            PatternFont::Serif => dejavu::serif::regular(),
+            PatternFont::SerifBold => dejavu::serif::bold(),
+        }
+    }
+
+    /// PostScript name of the font
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            PatternFont::Sans => "DejaVuSans",
+            PatternFont::SansBold => "DejaVuSans-Bold",
+            PatternFont::Mono => "DejaVuSansMono",
+            PatternFont::MonoBold => "DejaVuSansMono-Bold",
+            PatternFont::Serif => "DejaVuSerif",
+            PatternFont::SerifBold => "DejaVuSerif-Bold",
+        }
+    }
+}
+
+/// Drawing operations used by pattern renderer.
+/// Coordinates are in points, origin is in bottom left corner of the page
+pub trait PatternCanvas {
+    fn set_fill_color(&mut self, color: Rgb<u8>) -> io::Result<()>;
+    fn set_stroke_color(&mut self, color: Rgb<u8>) -> io::Result<()>;
+    fn set_line_width(&mut self, width: f32) -> io::Result<()>;
+
+    /// Shapes are added to current path, drawn by `fill` or `stroke`
+    fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> io::Result<()>;
+    fn circle(&mut self, x: f32, y: f32, radius: f32) -> io::Result<()>;
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> io::Result<()>;
+    fn fill(&mut self) -> io::Result<()>;
+    fn stroke(&mut self) -> io::Result<()>;
+
+    /// Text is drawn using fill color, `y` is the baseline
+    fn left_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()>;
+    fn center_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()>;
+    fn right_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()>;
+}
+
+pub trait PatternDocument {
+    fn render_page<F>(&mut self, width: f32, height: f32, render_contents: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn PatternCanvas) -> io::Result<()>;
+
+    /// Writes all pending content
+    fn finish(self) -> io::Result<()>;
+}
+
+/// Path element of backends drawing shapes on `fill` or `stroke`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PathShape {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32
+    },
+    Circle {
+        x: f32,
+        y: f32,
+        radius: f32
+    },
+    Line {
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GraphicsState {
+    pub fill_color: Rgb<u8>,
+    pub stroke_color: Rgb<u8>,
+    pub line_width: f32,
+    pub path: Vec<PathShape>,
+}
+
+impl Default for GraphicsState {
+    fn default() -> Self {
+        Self {
+            fill_color: Rgb([0, 0, 0]),
+            stroke_color: Rgb([0, 0, 0]),
+            line_width: 1.0,
+            path: Vec::new(),
+        }
+    }
+}
+
+/// Multi page outputs are split: `pattern.svg` -> `pattern_1.svg`, `pattern_2.svg`, ...
+pub(crate) fn get_numbered_page_path(path: &Path, page_number: usize) -> PathBuf {
+    let stem = path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("page");
+    let file_name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{stem}_{page_number}.{extension}"),
+        None => format!("{stem}_{page_number}"),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod test_backend {
+    use std::path::{
+        Path,
+        PathBuf
+    };
+
+    use super::get_numbered_page_path;
+
+    #[test]
+    fn test_numbered_page_path() {
+        assert_eq!(get_numbered_page_path(Path::new("out/pattern.svg"), 2), PathBuf::from("out/pattern_2.svg"));
+        assert_eq!(get_numbered_page_path(Path::new("pattern"), 1), PathBuf::from("pattern_1"));
+    }
+}
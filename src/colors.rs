@@ -0,0 +1,316 @@
+use ditherum::color::ColorRGB;
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+// D65 reference white
+const WHITE_X: f64 = 0.95047;
+const WHITE_Y: f64 = 1.0;
+const WHITE_Z: f64 = 1.08883;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorDistanceMetric {
+    /// Euclidean distance in sRGB
+    #[default]
+    Rgb,
+    DeltaE76,
+    /// Graphic arts weights
+    DeltaE94,
+    DeltaE2000,
+}
+
+pub fn srgb_channel_to_linear(channel: u8) -> f64 {
+    let channel = channel as f64 / 255.0;
+    if channel <= 0.04045 {
+        channel / 12.92
+    } else {
+        ((channel + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_channel_to_srgb(channel: f64) -> u8 {
+    let channel = channel.clamp(0.0, 1.0);
+    let channel = if channel <= 0.0031308 {
+        channel * 12.92
+    } else {
+        1.055 * channel.powf(1.0 / 2.4) - 0.055
+    };
+    (channel * 255.0).round() as u8
+}
+
+/// WCAG relative luminance, 0.0 for black up to 1.0 for white
+pub fn relative_luminance(color: &ColorRGB) -> f32 {
+    let [r, g, b] = color.0.map(srgb_channel_to_linear);
+    (0.2126 * r + 0.7152 * g + 0.0722 * b) as f32
+}
+
+/// WCAG contrast ratio, from 1.0 up to 21.0
+pub fn contrast_ratio(color1: &ColorRGB, color2: &ColorRGB) -> f32 {
+    let luminance1 = relative_luminance(color1);
+    let luminance2 = relative_luminance(color2);
+    (luminance1.max(luminance2) + 0.05) / (luminance1.min(luminance2) + 0.05)
+}
+
+/// Black or white, whichever contrasts more with background, along with contrast ratio
+pub fn get_contrasting_black_or_white(background: &ColorRGB) -> (ColorRGB, f32) {
+    let black = ColorRGB([0, 0, 0]);
+    let white = ColorRGB([255, 255, 255]);
+    let black_contrast = contrast_ratio(background, &black);
+    let white_contrast = contrast_ratio(background, &white);
+
+    if black_contrast >= white_contrast {
+        (black, black_contrast)
+    } else {
+        (white, white_contrast)
+    }
+}
+
+impl From<ColorRGB> for Lab {
+    fn from(value: ColorRGB) -> Self {
+        let [r, g, b] = value.0.map(srgb_channel_to_linear);
+
+        let x = 0.4124564 * r + 0.3575761 * g + 0.1804375 * b;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = 0.0193339 * r + 0.1191920 * g + 0.9503041 * b;
+
+        let f = |t: f64| {
+            const DELTA: f64 = 6.0 / 29.0;
+            if t > DELTA * DELTA * DELTA {
+                t.cbrt()
+            } else {
+                t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+            }
+        };
+        let (fx, fy, fz) = (f(x / WHITE_X), f(y / WHITE_Y), f(z / WHITE_Z));
+
+        Self {
+            l: (116.0 * fy - 16.0) as f32,
+            a: (500.0 * (fx - fy)) as f32,
+            b: (200.0 * (fy - fz)) as f32,
+        }
+    }
+}
+
+pub fn delta_e76(lab1: &Lab, lab2: &Lab) -> f32 {
+    ((lab1.l - lab2.l).powi(2) + (lab1.a - lab2.a).powi(2) + (lab1.b - lab2.b).powi(2)).sqrt()
+}
+
+pub fn delta_e94(lab1: &Lab, lab2: &Lab) -> f32 {
+    const K1: f32 = 0.045;
+    const K2: f32 = 0.015;
+
+    let c1 = lab1.a.hypot(lab1.b);
+    let c2 = lab2.a.hypot(lab2.b);
+    let delta_l = lab1.l - lab2.l;
+    let delta_c = c1 - c2;
+    let delta_h_squared = ((lab1.a - lab2.a).powi(2) + (lab1.b - lab2.b).powi(2) - delta_c.powi(2)).max(0.0);
+
+    let s_c = 1.0 + K1 * c1;
+    let s_h = 1.0 + K2 * c1;
+
+    (delta_l.powi(2) + (delta_c / s_c).powi(2) + delta_h_squared / s_h.powi(2)).sqrt()
+}
+
+pub fn delta_e2000(lab1: &Lab, lab2: &Lab) -> f32 {
+    let (l1, a1, b1) = (lab1.l as f64, lab1.a as f64, lab1.b as f64);
+    let (l2, a2, b2) = (lab2.l as f64, lab2.a as f64, lab2.b as f64);
+    let pow25_7 = 25.0_f64.powi(7);
+
+    let c_bar = (a1.hypot(b1) + a2.hypot(b2)) / 2.0;
+    let g = 0.5 * (1.0 - (c_bar.powi(7) / (c_bar.powi(7) + pow25_7)).sqrt());
+    let a1_prime = (1.0 + g) * a1;
+    let a2_prime = (1.0 + g) * a2;
+    let c1_prime = a1_prime.hypot(b1);
+    let c2_prime = a2_prime.hypot(b2);
+
+    let hue_angle = |b: f64, a_prime: f64| {
+        if b == 0.0 && a_prime == 0.0 {
+            0.0
+        } else {
+            b.atan2(a_prime).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1_prime = hue_angle(b1, a1_prime);
+    let h2_prime = hue_angle(b2, a2_prime);
+
+    let delta_l_prime = l2 - l1;
+    let delta_c_prime = c2_prime - c1_prime;
+    let chroma_product = c1_prime * c2_prime;
+    let delta_h_prime = if chroma_product == 0.0 {
+        0.0
+    } else {
+        let diff = h2_prime - h1_prime;
+        if diff > 180.0 {
+            diff - 360.0
+        } else if diff < -180.0 {
+            diff + 360.0
+        } else {
+            diff
+        }
+    };
+    let delta_big_h_prime = 2.0 * chroma_product.sqrt() * (delta_h_prime.to_radians() / 2.0).sin();
+
+    let l_bar_prime = (l1 + l2) / 2.0;
+    let c_bar_prime = (c1_prime + c2_prime) / 2.0;
+    let h_bar_prime = if chroma_product == 0.0 {
+        h1_prime + h2_prime
+    } else if (h1_prime - h2_prime).abs() <= 180.0 {
+        (h1_prime + h2_prime) / 2.0
+    } else if h1_prime + h2_prime < 360.0 {
+        (h1_prime + h2_prime + 360.0) / 2.0
+    } else {
+        (h1_prime + h2_prime - 360.0) / 2.0
+    };
+
+    let t = 1.0
+        - 0.17 * (h_bar_prime - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * h_bar_prime).to_radians().cos()
+        + 0.32 * (3.0 * h_bar_prime + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * h_bar_prime - 63.0).to_radians().cos();
+    let delta_theta = 30.0 * (-((h_bar_prime - 275.0) / 25.0).powi(2)).exp();
+    let r_c = 2.0 * (c_bar_prime.powi(7) / (c_bar_prime.powi(7) + pow25_7)).sqrt();
+    let s_l = 1.0 + 0.015 * (l_bar_prime - 50.0).powi(2) / (20.0 + (l_bar_prime - 50.0).powi(2)).sqrt();
+    let s_c = 1.0 + 0.045 * c_bar_prime;
+    let s_h = 1.0 + 0.015 * c_bar_prime * t;
+    let r_t = -(2.0 * delta_theta).to_radians().sin() * r_c;
+
+    let l_term = delta_l_prime / s_l;
+    let c_term = delta_c_prime / s_c;
+    let h_term = delta_big_h_prime / s_h;
+
+    (l_term.powi(2) + c_term.powi(2) + h_term.powi(2) + r_t * c_term * h_term).sqrt() as f32
+}
+
+impl ColorDistanceMetric {
+    pub fn distance(&self, color1: &ColorRGB, color2: &ColorRGB) -> f32 {
+        match self {
+            ColorDistanceMetric::Rgb => (0..3)
+                .map(|idx| (color1.0[idx] as f32 - color2.0[idx] as f32).powi(2))
+                .sum::<f32>()
+                .sqrt(),
+            ColorDistanceMetric::DeltaE76 => delta_e76(&Lab::from(*color1), &Lab::from(*color2)),
+            ColorDistanceMetric::DeltaE94 => delta_e94(&Lab::from(*color1), &Lab::from(*color2)),
+            ColorDistanceMetric::DeltaE2000 => delta_e2000(&Lab::from(*color1), &Lab::from(*color2)),
+        }
+    }
+
+    /// Distance below which colors are considered similar
+    pub fn get_close_colors_threshold(&self) -> f32 {
+        match self {
+            ColorDistanceMetric::Rgb => 100.0,
+            ColorDistanceMetric::DeltaE76 => 25.0,
+            ColorDistanceMetric::DeltaE94 => 15.0,
+            ColorDistanceMetric::DeltaE2000 => 15.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_colors {
+    use ditherum::color::ColorRGB;
+
+    use super::{
+        contrast_ratio,
+        delta_e2000,
+        delta_e76,
+        delta_e94,
+        get_contrasting_black_or_white,
+        relative_luminance,
+        ColorDistanceMetric,
+        Lab
+    };
+
+    fn assert_close(value: f32, expected: f32, tolerance: f32) {
+        assert!((value - expected).abs() <= tolerance, "value={value}, expected={expected}");
+    }
+
+    #[test]
+    fn test_srgb_to_lab() {
+        let white = Lab::from(ColorRGB([255, 255, 255]));
+        assert_close(white.l, 100.0, 0.01);
+        assert_close(white.a, 0.0, 0.01);
+        assert_close(white.b, 0.0, 0.01);
+
+        let black = Lab::from(ColorRGB([0, 0, 0]));
+        assert_close(black.l, 0.0, 0.01);
+
+        let red = Lab::from(ColorRGB([255, 0, 0]));
+        assert_close(red.l, 53.24, 0.05);
+        assert_close(red.a, 80.09, 0.05);
+        assert_close(red.b, 67.20, 0.05);
+    }
+
+    #[test]
+    fn test_delta_e2000_reference_pairs() {
+        // Sharma, Wu, Dalal test data
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((50.0, 2.5, 0.0), (50.0, 0.0, -2.5), 4.3065),
+            ((2.0776, 0.0795, -1.1350), (0.9033, -0.0636, -0.5514), 0.9082),
+        ];
+
+        for ((l1, a1, b1), (l2, a2, b2), expected) in pairs {
+            let lab1 = Lab { l: l1, a: a1, b: b1 };
+            let lab2 = Lab { l: l2, a: a2, b: b2 };
+            assert_close(delta_e2000(&lab1, &lab2), expected, 0.0005);
+            assert_close(delta_e2000(&lab2, &lab1), expected, 0.0005);
+        }
+    }
+
+    #[test]
+    fn test_delta_e76_and_e94() {
+        let lab1 = Lab { l: 50.0, a: 0.0, b: 0.0 };
+        let lab2 = Lab { l: 53.0, a: 4.0, b: 0.0 };
+        assert_close(delta_e76(&lab1, &lab2), 5.0, 0.0001);
+        // Neutral reference color has no chroma weighting
+        assert_close(delta_e94(&lab1, &lab2), 5.0, 0.0001);
+    }
+
+    #[test]
+    fn test_same_color_zero_distance() {
+        let color = ColorRGB([12, 200, 77]);
+        for metric in [ColorDistanceMetric::Rgb, ColorDistanceMetric::DeltaE76, ColorDistanceMetric::DeltaE94, ColorDistanceMetric::DeltaE2000] {
+            assert_close(metric.distance(&color, &color), 0.0, 0.0001);
+        }
+    }
+
+    #[test]
+    fn test_wcag_contrast() {
+        let black = ColorRGB([0, 0, 0]);
+        let white = ColorRGB([255, 255, 255]);
+        assert_close(relative_luminance(&black), 0.0, 0.0001);
+        assert_close(relative_luminance(&white), 1.0, 0.0001);
+        assert_close(contrast_ratio(&black, &white), 21.0, 0.001);
+        assert_close(contrast_ratio(&white, &black), 21.0, 0.001);
+        assert_close(contrast_ratio(&white, &white), 1.0, 0.001);
+    }
+
+    #[test]
+    fn test_contrasting_symbol_color() {
+        // Raw channels sum misjudged saturated green as dark
+        let (color, contrast) = get_contrasting_black_or_white(&ColorRGB([0, 255, 0]));
+        assert_eq!(color, ColorRGB([0, 0, 0]));
+        assert!(contrast > 7.0);
+
+        let (color, _) = get_contrasting_black_or_white(&ColorRGB([255, 255, 0]));
+        assert_eq!(color, ColorRGB([0, 0, 0]));
+
+        let (color, _) = get_contrasting_black_or_white(&ColorRGB([0, 0, 255]));
+        assert_eq!(color, ColorRGB([255, 255, 255]));
+
+        // Mid tones can't reach high contrast with neither of them
+        let (_, contrast) = get_contrasting_black_or_white(&ColorRGB([118, 118, 118]));
+        assert!(contrast < 7.0);
+    }
+}
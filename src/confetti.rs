@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use ditherum::color::ColorRGB;
+use image::{
+    Rgb,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    colors::ColorDistanceMetric,
+    mask::CellsMask
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfettiReduction {
+    /// Clusters up to this many cells get merged, 1 merges isolated cells only
+    pub strength: usize,
+    /// Neighbour color further than that is never used for merging, in units of used metric.
+    /// Close colors threshold of used metric when not set
+    pub max_color_distance: Option<f32>,
+}
+
+impl Default for ConfettiReduction {
+    fn default() -> Self {
+        Self {
+            strength: 1,
+            max_color_distance: None
+        }
+    }
+}
+
+impl ConfettiReduction {
+    pub fn get_max_color_distance(&self, metric: ColorDistanceMetric) -> f32 {
+        self.max_color_distance.unwrap_or(metric.get_close_colors_threshold())
+    }
+}
+
+fn get_neighbours(x: u32, y: u32, width: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    [(-1, 0), (1, 0), (0, -1), (0, 1)]
+        .into_iter()
+        .map(move |(dx, dy)| (x as i64 + dx, y as i64 + dy))
+        .filter(move |(nx, ny)| *nx >= 0 && *ny >= 0 && *nx < width as i64 && *ny < height as i64)
+        .map(|(nx, ny)| (nx as u32, ny as u32))
+}
+
+/// Same color 4-connected cells, fixed cells are never part of cluster
+fn find_cluster(
+    img: &RgbImage,
+    fixed_cells: &CellsMask,
+    visited: &mut [bool],
+    start_x: u32,
+    start_y: u32
+) -> Vec<(u32, u32)> {
+    let (width, height) = img.dimensions();
+    let color = img.get_pixel(start_x, start_y);
+    let mut cluster = vec![(start_x, start_y)];
+    visited[(start_y * width + start_x) as usize] = true;
+
+    let mut idx = 0;
+    while idx < cluster.len() {
+        let (x, y) = cluster[idx];
+        idx += 1;
+        for (nx, ny) in get_neighbours(x, y, width, height) {
+            let visited_cell = &mut visited[(ny * width + nx) as usize];
+            if !*visited_cell && !fixed_cells.is_empty_cell(nx, ny) && img.get_pixel(nx, ny) == color {
+                *visited_cell = true;
+                cluster.push((nx, ny));
+            }
+        }
+    }
+    cluster
+}
+
+/// Picks most common bordering color, ties resolved by closeness, fixed cells colors are skipped
+fn find_merge_color(
+    img: &RgbImage,
+    fixed_cells: &CellsMask,
+    cluster: &[(u32, u32)],
+    max_color_distance: f32,
+    metric: ColorDistanceMetric
+) -> Option<Rgb<u8>> {
+    let (width, height) = img.dimensions();
+    let cluster_color = img.get_pixel(cluster[0].0, cluster[0].1);
+
+    let mut border_colors: HashMap<[u8; 3], usize> = HashMap::new();
+    cluster.iter().for_each(|(x, y)| {
+        get_neighbours(*x, *y, width, height)
+            .filter(|(nx, ny)| !fixed_cells.is_empty_cell(*nx, *ny))
+            .map(|(nx, ny)| img.get_pixel(nx, ny))
+            .filter(|neighbour_color| *neighbour_color != cluster_color)
+            .for_each(|neighbour_color| {
+                *border_colors.entry(neighbour_color.0).or_insert(0) += 1;
+            });
+    });
+
+    border_colors.into_iter()
+        .map(|(color, count)| (Rgb(color), count, metric.distance(&ColorRGB::from(*cluster_color), &ColorRGB(color))))
+        .filter(|(_, _, distance)| *distance <= max_color_distance)
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.2.total_cmp(&a.2)))
+        .map(|(color, _, _)| color)
+}
+
+/// Merges clusters seen against current grid, returns count of merged clusters.
+/// Cluster bordering cells changed earlier in the pass is left for the next one
+fn reduce_confetti_pass(
+    img: &mut RgbImage,
+    fixed_cells: &CellsMask,
+    confetti_reduction: &ConfettiReduction,
+    metric: ColorDistanceMetric
+) -> usize {
+    let (width, height) = img.dimensions();
+    let mut visited = vec![false; (width * height) as usize];
+    let mut changed = vec![false; (width * height) as usize];
+    let mut merged_clusters = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            if visited[(y * width + x) as usize] || fixed_cells.is_empty_cell(x, y) {
+                continue;
+            }
+
+            let cluster = find_cluster(img, fixed_cells, &mut visited, x, y);
+            if cluster.len() > confetti_reduction.strength {
+                continue;
+            }
+
+            let borders_changed_cell = cluster.iter()
+                .flat_map(|(cx, cy)| get_neighbours(*cx, *cy, width, height))
+                .any(|(nx, ny)| changed[(ny * width + nx) as usize]);
+            if borders_changed_cell {
+                continue;
+            }
+
+            if let Some(merge_color) = find_merge_color(img, fixed_cells, &cluster, confetti_reduction.get_max_color_distance(metric), metric) {
+                cluster.iter().for_each(|(cx, cy)| {
+                    img.put_pixel(*cx, *cy, merge_color);
+                    changed[(cy * width + cx) as usize] = true;
+                });
+                merged_clusters += 1;
+            }
+        }
+    }
+    merged_clusters
+}
+
+/// Merges small clusters into neighbouring color, returns count of changed cells.
+/// Cells marked empty in `fixed_cells`, e.g. masked out or letterbox border ones, are left as they are
+pub fn reduce_confetti(
+    img: &mut RgbImage,
+    fixed_cells: &CellsMask,
+    confetti_reduction: &ConfettiReduction,
+    metric: ColorDistanceMetric
+) -> usize {
+    let original_img = img.clone();
+
+    // Every merge joins two clusters, so passes end
+    while reduce_confetti_pass(img, fixed_cells, confetti_reduction, metric) > 0 {}
+
+    img.pixels()
+        .zip(original_img.pixels())
+        .filter(|(pixel, original_pixel)| pixel != original_pixel)
+        .count()
+}
+
+#[cfg(test)]
+mod test_confetti {
+    use image::{
+        GrayImage,
+        Luma,
+        Rgb,
+        RgbImage
+    };
+
+    use crate::{
+        colors::ColorDistanceMetric,
+        mask::CellsMask
+    };
+
+    use super::{
+        reduce_confetti,
+        ConfettiReduction
+    };
+
+    const GRAY: Rgb<u8> = Rgb([120, 120, 120]);
+    const LIGHT_GRAY: Rgb<u8> = Rgb([140, 140, 140]);
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+    #[test]
+    fn test_isolated_cell_merged() {
+        let mut img = RgbImage::from_pixel(5, 5, GRAY);
+        img.put_pixel(2, 2, LIGHT_GRAY);
+
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 1);
+        assert!(img.pixels().all(|px| *px == GRAY));
+    }
+
+    #[test]
+    fn test_distant_color_kept() {
+        let mut img = RgbImage::from_pixel(5, 5, GRAY);
+        img.put_pixel(2, 2, RED);
+
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 0);
+        assert_eq!(*img.get_pixel(2, 2), RED);
+    }
+
+    #[test]
+    fn test_lab_metric_threshold() {
+        let mut img = RgbImage::from_pixel(5, 5, GRAY);
+        img.put_pixel(2, 2, LIGHT_GRAY);
+
+        // Default distance follows metric, RGB scale one would merge distant colors too
+        let metric = ColorDistanceMetric::DeltaE2000;
+        let confetti_reduction = ConfettiReduction::default();
+        assert_eq!(confetti_reduction.get_max_color_distance(metric), metric.get_close_colors_threshold());
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &confetti_reduction, metric);
+        assert_eq!(changed, 1);
+    }
+
+    #[test]
+    fn test_strength_limits_cluster_size() {
+        let mut img = RgbImage::from_pixel(6, 6, GRAY);
+        img.put_pixel(2, 2, LIGHT_GRAY);
+        img.put_pixel(3, 2, LIGHT_GRAY);
+
+        let mut weak_img = img.clone();
+        let changed = reduce_confetti(&mut weak_img, &CellsMask::default(), &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 0);
+
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &ConfettiReduction { strength: 2, ..Default::default() }, ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 2);
+        assert!(img.pixels().all(|px| *px == GRAY));
+    }
+
+    #[test]
+    fn test_adjacent_singletons_not_swapped() {
+        // Each singleton is the only close neighbour of the other one
+        let mut img = RgbImage::from_pixel(5, 5, RED);
+        img.put_pixel(2, 2, GRAY);
+        img.put_pixel(3, 2, LIGHT_GRAY);
+
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 1);
+        assert_eq!(img.get_pixel(2, 2), img.get_pixel(3, 2));
+    }
+
+    #[test]
+    fn test_fixed_cells_kept() {
+        // Left column is fixed, e.g. letterbox border
+        let mut img = RgbImage::from_pixel(5, 5, RED);
+        (0..5).for_each(|y| img.put_pixel(0, y, LIGHT_GRAY));
+        img.put_pixel(0, 4, GRAY);
+        img.put_pixel(1, 2, GRAY);
+        let fixed_cells = CellsMask::from_coverage(&GrayImage::from_fn(5, 5, |x, _| Luma([if x == 0 { 0 } else { 255 }])));
+
+        let changed = reduce_confetti(&mut img, &fixed_cells, &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 0);
+        // Isolated fixed cell is not merged
+        assert_eq!(*img.get_pixel(0, 4), GRAY);
+        // Only close neighbour is fixed, so its color is not used
+        assert_eq!(*img.get_pixel(1, 2), GRAY);
+
+        let changed = reduce_confetti(&mut img, &CellsMask::default(), &ConfettiReduction::default(), ColorDistanceMetric::Rgb);
+        assert_eq!(changed, 2);
+    }
+}
@@ -0,0 +1,387 @@
+use std::path::{
+    Path,
+    PathBuf
+};
+
+use serde::{
+    de::Error,
+    Deserialize,
+    Deserializer,
+    Serialize
+};
+
+use crate::{
+    adjustments::ImageAdjustments,
+    colors::ColorDistanceMetric,
+    confetti::ConfettiReduction,
+    config_file::{
+        is_toml_path,
+        load_toml_or_json
+    },
+    dithering::DitheringOptions,
+    dmc::{
+        DrillBagsConfig,
+        PALETTE_PATH
+    },
+    generator::PaletteSelection,
+    mask::MaskOptions,
+    paper::parse_paper_sheet,
+    preview::PreviewOptions,
+    render::{
+        PatternOutput,
+        PatternPages
+    },
+    sizing::CanvasSizing,
+    style::RenderStyle,
+    types::{
+        DiamondShape,
+        PaperSheet
+    }
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum GenerationConfigError {
+    #[error("IoError, reason={0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("SerdeJsonError, reason={0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("TomlError, reason={0}")]
+    TomlError(#[from] toml::de::Error),
+
+    #[error("TomlSerializeError, reason={0}")]
+    TomlSerializeError(#[from] toml::ser::Error),
+}
+
+/// Where pattern colors come from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaletteConfig {
+    /// DMC records or RGB triplets JSON file
+    pub path: PathBuf,
+    pub selection: PaletteSelection,
+    /// Limited to available symbols count
+    pub max_colors: usize,
+}
+
+/// Everything needed to generate a pattern, paths are relative to working directory.
+/// Missing fields in loaded file take default values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationConfig {
+    pub image_path: PathBuf,
+    /// Preset name like "A3", size like "420x594mm" or size with margins in mm
+    #[serde(deserialize_with = "deserialize_paper_sheet")]
+    pub paper_sheet: PaperSheet,
+    pub diamond_shape: DiamondShape,
+    pub palette: PaletteConfig,
+    pub color_distance_metric: ColorDistanceMetric,
+    pub dithering: DitheringOptions,
+    pub confetti_reduction: Option<ConfettiReduction>,
+    pub adjustments: ImageAdjustments,
+    pub mask: MaskOptions,
+    pub canvas_sizing: CanvasSizing,
+    pub drill_bags: DrillBagsConfig,
+    pub pattern_pages: PatternPages,
+    pub style: RenderStyle,
+    /// Grid lines on color pages too, symbols pages always have them
+    pub grid: bool,
+    pub outputs: Vec<PatternOutput>,
+    /// Finished canvas preview PNG
+    pub preview_path: Option<PathBuf>,
+    pub preview: PreviewOptions,
+    /// Used DMC colors with counts JSON
+    pub dmc_palette_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerationConfigBuilder {
+    config: GenerationConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaperSheetData {
+    Named(String),
+    Sheet(PaperSheet),
+}
+
+fn deserialize_paper_sheet<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PaperSheet, D::Error> {
+    match PaperSheetData::deserialize(deserializer)? {
+        PaperSheetData::Named(name) => parse_paper_sheet(&name).map_err(D::Error::custom),
+        PaperSheetData::Sheet(paper_sheet) => Ok(paper_sheet),
+    }
+}
+
+impl Default for PaletteConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(PALETTE_PATH),
+            selection: PaletteSelection::default(),
+            max_colors: 12
+        }
+    }
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            image_path: PathBuf::new(),
+            paper_sheet: PaperSheet::standard_a4(),
+            diamond_shape: DiamondShape::common_round(),
+            palette: PaletteConfig::default(),
+            color_distance_metric: ColorDistanceMetric::default(),
+            dithering: DitheringOptions::default(),
+            confetti_reduction: None,
+            adjustments: ImageAdjustments::default(),
+            mask: MaskOptions::default(),
+            canvas_sizing: CanvasSizing::default(),
+            drill_bags: DrillBagsConfig::default(),
+            pattern_pages: PatternPages::default(),
+            style: RenderStyle::default(),
+            grid: false,
+            outputs: vec![PatternOutput::pdf("pattern.pdf")],
+            preview_path: None,
+            preview: PreviewOptions::default(),
+            dmc_palette_path: None,
+        }
+    }
+}
+
+impl GenerationConfig {
+    pub fn builder<P: Into<PathBuf>>(image_path: P) -> GenerationConfigBuilder {
+        GenerationConfigBuilder {
+            config: GenerationConfig {
+                image_path: image_path.into(),
+                ..Default::default()
+            }
+        }
+    }
+
+    /// TOML if file has `.toml` extension, JSON otherwise
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, GenerationConfigError> {
+        load_toml_or_json(path)
+    }
+
+    /// Same format rules as `load_from`, saved file reproduces the job
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<(), GenerationConfigError> {
+        let data = if is_toml_path(path.as_ref()) {
+            toml::to_string_pretty(self)?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+}
+
+impl GenerationConfigBuilder {
+    pub fn paper_sheet(mut self, paper_sheet: PaperSheet) -> Self {
+        self.config.paper_sheet = paper_sheet;
+        self
+    }
+
+    pub fn diamond_shape(mut self, diamond_shape: DiamondShape) -> Self {
+        self.config.diamond_shape = diamond_shape;
+        self
+    }
+
+    pub fn palette_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.palette.path = path.into();
+        self
+    }
+
+    pub fn palette_selection(mut self, palette_selection: PaletteSelection) -> Self {
+        self.config.palette.selection = palette_selection;
+        self
+    }
+
+    pub fn max_colors(mut self, max_colors: usize) -> Self {
+        self.config.palette.max_colors = max_colors;
+        self
+    }
+
+    pub fn color_distance_metric(mut self, color_distance_metric: ColorDistanceMetric) -> Self {
+        self.config.color_distance_metric = color_distance_metric;
+        self
+    }
+
+    pub fn dithering(mut self, dithering_options: DitheringOptions) -> Self {
+        self.config.dithering = dithering_options;
+        self
+    }
+
+    pub fn confetti_reduction(mut self, confetti_reduction: Option<ConfettiReduction>) -> Self {
+        self.config.confetti_reduction = confetti_reduction;
+        self
+    }
+
+    pub fn adjustments(mut self, image_adjustments: ImageAdjustments) -> Self {
+        self.config.adjustments = image_adjustments;
+        self
+    }
+
+    pub fn mask(mut self, mask_options: MaskOptions) -> Self {
+        self.config.mask = mask_options;
+        self
+    }
+
+    pub fn canvas_sizing(mut self, canvas_sizing: CanvasSizing) -> Self {
+        self.config.canvas_sizing = canvas_sizing;
+        self
+    }
+
+    pub fn drill_bags(mut self, drill_bags_config: DrillBagsConfig) -> Self {
+        self.config.drill_bags = drill_bags_config;
+        self
+    }
+
+    pub fn pattern_pages(mut self, pattern_pages: PatternPages) -> Self {
+        self.config.pattern_pages = pattern_pages;
+        self
+    }
+
+    pub fn style(mut self, render_style: RenderStyle) -> Self {
+        self.config.style = render_style;
+        self
+    }
+
+    pub fn grid(mut self, draw_grid_lines: bool) -> Self {
+        self.config.grid = draw_grid_lines;
+        self
+    }
+
+    /// Replaces default PDF output
+    pub fn outputs(mut self, outputs: Vec<PatternOutput>) -> Self {
+        self.config.outputs = outputs;
+        self
+    }
+
+    pub fn preview<P: Into<PathBuf>>(mut self, path: P, preview_options: PreviewOptions) -> Self {
+        self.config.preview_path = Some(path.into());
+        self.config.preview = preview_options;
+        self
+    }
+
+    pub fn dmc_palette_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config.dmc_palette_path = Some(path.into());
+        self
+    }
+
+    pub fn build(self) -> GenerationConfig {
+        self.config
+    }
+}
+
+#[cfg(test)]
+mod test_config {
+    use crate::{
+        colors::ColorDistanceMetric,
+        dithering::DitheringAlgorithm,
+        render::{
+            OutputFormat,
+            PatternOutput
+        },
+        sizing::{
+            AspectFit,
+            SizingPolicy
+        },
+        types::{
+            DiamondShape,
+            PaperSheet,
+            Size2U
+        }
+    };
+
+    use super::GenerationConfig;
+
+    #[test]
+    fn test_partial_toml_config() {
+        let config: GenerationConfig = toml::from_str(r#"
+            image_path = "res/test_pink_300.jpg"
+            paper_sheet = "A3"
+            diamond_shape = { shape = "square", side = 2.5 }
+            grid = true
+
+            [palette]
+            max_colors = 20
+
+            [dithering]
+            algorithm = "atkinson"
+
+            [canvas_sizing]
+            policy = { canvas_drills = { w = 160, h = 120 } }
+            aspect_fit = "letterbox"
+            border_dmc_code = "310"
+
+            [[outputs]]
+            format = "png"
+            dpi = 150.0
+            path = "pattern.png"
+        "#).unwrap();
+
+        assert_eq!(config.paper_sheet.size.h.raw_value(), 420.0);
+        assert!(matches!(config.diamond_shape, DiamondShape::Square { side } if side.raw_value() == 2.5));
+        assert_eq!(config.palette.max_colors, 20);
+        assert_eq!(config.palette.path, GenerationConfig::default().palette.path);
+        assert_eq!(config.dithering.algorithm, DitheringAlgorithm::Atkinson);
+        assert!(matches!(config.canvas_sizing.policy, SizingPolicy::CanvasDrills(Size2U { w: 160, h: 120 })));
+        assert_eq!(config.canvas_sizing.aspect_fit, AspectFit::Letterbox);
+        assert_eq!(config.canvas_sizing.overlap_cells, 2);
+        assert_eq!(config.outputs, vec![PatternOutput { format: OutputFormat::Png { dpi: 150.0 }, path: "pattern.png".to_string() }]);
+    }
+
+    #[test]
+    fn test_confetti_distance_follows_metric() {
+        let config: GenerationConfig = toml::from_str(r#"
+            color_distance_metric = "delta_e2000"
+
+            [confetti_reduction]
+            strength = 2
+        "#).unwrap();
+
+        let confetti_reduction = config.confetti_reduction.unwrap();
+        assert_eq!(confetti_reduction.strength, 2);
+        assert_eq!(
+            confetti_reduction.get_max_color_distance(config.color_distance_metric),
+            ColorDistanceMetric::DeltaE2000.get_close_colors_threshold()
+        );
+    }
+
+    #[test]
+    fn test_unknown_paper_preset() {
+        let result = serde_json::from_str::<GenerationConfig>(r#"{ "paper_sheet": "A9" }"#);
+        assert!(result.unwrap_err().to_string().contains("A9"));
+    }
+
+    #[test]
+    fn test_saved_config_round_trip() {
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .paper_sheet(PaperSheet::standard_a3())
+            .max_colors(24)
+            .canvas_sizing(crate::sizing::CanvasSizing {
+                policy: SizingPolicy::DrillBudget(5000),
+                focal_point: Some((0.3, 0.6)),
+                ..Default::default()
+            })
+            .grid(true)
+            .build();
+
+        for extension in ["toml", "json"] {
+            let path = std::env::temp_dir().join(format!("diamonds_imager_generator_config.{extension}"));
+            config.save_to(&path).unwrap();
+            let loaded = GenerationConfig::load_from(&path).unwrap();
+
+            assert_eq!(loaded.image_path, config.image_path);
+            assert_eq!(loaded.paper_sheet.size.w.raw_value(), 297.0);
+            assert_eq!(loaded.palette, config.palette);
+            assert!(matches!(loaded.canvas_sizing.policy, SizingPolicy::DrillBudget(5000)));
+            assert_eq!(loaded.canvas_sizing.focal_point, Some((0.3, 0.6)));
+            assert_eq!(loaded.outputs, config.outputs);
+            assert_eq!(loaded.style, config.style);
+            assert!(loaded.grid);
+        }
+    }
+}
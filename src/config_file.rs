@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+
+pub(crate) fn is_toml_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+/// TOML if file has `.toml` extension, JSON otherwise.
+/// Errors are converted into caller's error type
+pub(crate) fn load_toml_or_json<T, E, P>(path: P) -> Result<T, E>
+where
+    T: DeserializeOwned,
+    E: From<std::io::Error> + From<serde_json::Error> + From<toml::de::Error>,
+    P: AsRef<Path>
+{
+    let data = std::fs::read_to_string(&path)?;
+
+    if is_toml_path(path.as_ref()) {
+        Ok(toml::from_str(&data)?)
+    } else {
+        Ok(serde_json::from_str(&data)?)
+    }
+}
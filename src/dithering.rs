@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use ditherum::{
+    algorithms::dithering::dithering_floyd_steinberg_rgb,
+    color::ColorRGB,
+    palette::PaletteRGB
+};
+use image::{
+    Rgb,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    colors::ColorDistanceMetric,
+    dmc::{
+        DmcError,
+        PaletteDmc
+    }
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DitheringAlgorithm {
+    /// Nearest palette color, no dithering
+    Nearest,
+    /// Ordered dithering using 4x4 Bayer matrix
+    Bayer,
+    #[default]
+    FloydSteinberg,
+    Atkinson,
+    JarvisJudiceNinke,
+    Stucki,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DitheringOptions {
+    pub algorithm: DitheringAlgorithm,
+    /// Alternate rows scanning direction, applies to error diffusion only
+    pub serpentine: bool,
+}
+
+/// Accumulated error is kept in range, otherwise saturated areas build it up into long streaks of one color
+const DIFFUSED_CHANNEL_MIN: f32 = -255.0;
+const DIFFUSED_CHANNEL_MAX: f32 = 510.0;
+
+/// Error diffusion kernel entry: (dx, dy, weight)
+type DiffusionKernel = (&'static [(i32, i32, f32)], f32);
+
+const FLOYD_STEINBERG_KERNEL: DiffusionKernel = (&[
+    (1, 0, 7.0),
+    (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0),
+], 16.0);
+
+// Diffuses only 6/8 of error, keeps more contrast
+const ATKINSON_KERNEL: DiffusionKernel = (&[
+    (1, 0, 1.0), (2, 0, 1.0),
+    (-1, 1, 1.0), (0, 1, 1.0), (1, 1, 1.0),
+    (0, 2, 1.0),
+], 8.0);
+
+const JARVIS_JUDICE_NINKE_KERNEL: DiffusionKernel = (&[
+    (1, 0, 7.0), (2, 0, 5.0),
+    (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+    (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+], 48.0);
+
+const STUCKI_KERNEL: DiffusionKernel = (&[
+    (1, 0, 8.0), (2, 0, 4.0),
+    (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 8.0), (1, 1, 4.0), (2, 1, 2.0),
+    (-2, 2, 1.0), (-1, 2, 2.0), (0, 2, 4.0), (1, 2, 2.0), (2, 2, 1.0),
+], 42.0);
+
+const BAYER_MATRIX_4X4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// Palette must not be empty
+pub(crate) struct NearestColorFinder<'a> {
+    palette: &'a [ColorRGB],
+    metric: ColorDistanceMetric,
+    cache: HashMap<[u8; 3], ColorRGB>,
+}
+
+impl<'a> NearestColorFinder<'a> {
+    pub(crate) fn new(palette: &'a [ColorRGB], metric: ColorDistanceMetric) -> Self {
+        Self {
+            palette,
+            metric,
+            cache: HashMap::new()
+        }
+    }
+
+    pub(crate) fn find(&mut self, color: [f32; 3]) -> ColorRGB {
+        let key = ColorRGB(color.map(|channel| channel.round().clamp(0.0, 255.0) as u8));
+        let palette = self.palette;
+        let metric = self.metric;
+        *self.cache.entry(key.0)
+            .or_insert_with(|| {
+                *palette.iter()
+                    .min_by(|a, b| metric.distance(&key, a).total_cmp(&metric.distance(&key, b)))
+                    .expect("Palette not empty")
+            })
+    }
+}
+
+fn dithering_nearest(mut img: RgbImage, palette: &[ColorRGB], metric: ColorDistanceMetric) -> RgbImage {
+    let mut finder = NearestColorFinder::new(palette, metric);
+    img.pixels_mut().for_each(|px| {
+        px.0 = finder.find(px.0.map(|channel| channel as f32)).0;
+    });
+    img
+}
+
+fn dithering_bayer(mut img: RgbImage, palette: &[ColorRGB], metric: ColorDistanceMetric) -> RgbImage {
+    // Threshold amplitude roughly equal to distance between palette colors
+    let spread = 255.0 / (palette.len() as f32).cbrt().max(1.0);
+    let mut finder = NearestColorFinder::new(palette, metric);
+    img.enumerate_pixels_mut().for_each(|(x, y, px)| {
+        let threshold = (BAYER_MATRIX_4X4[(y % 4) as usize][(x % 4) as usize] + 0.5) / 16.0 - 0.5;
+        px.0 = finder.find(px.0.map(|channel| channel as f32 + threshold * spread)).0;
+    });
+    img
+}
+
+fn dithering_error_diffusion(
+    img: RgbImage,
+    palette: &[ColorRGB],
+    metric: ColorDistanceMetric,
+    kernel: DiffusionKernel,
+    serpentine: bool
+) -> RgbImage {
+    let (width, height) = img.dimensions();
+    let (kernel_entries, kernel_divisor) = kernel;
+    let mut buffer: Vec<[f32; 3]> = img.pixels()
+        .map(|px| px.0.map(|channel| channel as f32))
+        .collect();
+    let mut finder = NearestColorFinder::new(palette, metric);
+    let mut result = RgbImage::new(width, height);
+
+    for y in 0..height {
+        let reversed = serpentine && y % 2 == 1;
+        let direction = if reversed { -1 } else { 1 };
+
+        for step in 0..width {
+            let x = if reversed { width - 1 - step } else { step };
+            let old_color = buffer[(y * width + x) as usize];
+            let new_color = finder.find(old_color);
+            result.put_pixel(x, y, Rgb(new_color.0));
+
+            let error = [0, 1, 2].map(|idx| old_color[idx] - new_color.0[idx] as f32);
+
+            kernel_entries.iter().for_each(|(dx, dy, weight)| {
+                let nx = x as i32 + dx * direction;
+                let ny = y as i32 + dy;
+                if nx < 0 || nx >= width as i32 || ny >= height as i32 {
+                    return;
+                }
+                let neighbour = &mut buffer[(ny as u32 * width + nx as u32) as usize];
+                (0..3).for_each(|idx| {
+                    neighbour[idx] = (neighbour[idx] + error[idx] * weight / kernel_divisor)
+                        .clamp(DIFFUSED_CHANNEL_MIN, DIFFUSED_CHANNEL_MAX);
+                });
+            });
+        }
+    }
+
+    result
+}
+
+pub fn dither_image(
+    img: RgbImage,
+    palette: &PaletteDmc,
+    dithering_options: &DitheringOptions,
+    metric: ColorDistanceMetric
+) -> Result<RgbImage, DmcError> {
+    if palette.is_empty() {
+        return Err(DmcError::EmptyPalette);
+    }
+
+    let palette_colors = palette.iter()
+        .map(|dmc| dmc.color)
+        .collect::<Vec<_>>();
+    let serpentine = dithering_options.serpentine;
+
+    let dithered_img = match dithering_options.algorithm {
+        DitheringAlgorithm::Nearest => dithering_nearest(img, &palette_colors, metric),
+        DitheringAlgorithm::Bayer => dithering_bayer(img, &palette_colors, metric),
+        // Default output stays as ditherum makes it
+        DitheringAlgorithm::FloydSteinberg if !serpentine && metric == ColorDistanceMetric::Rgb => {
+            dithering_floyd_steinberg_rgb(img, PaletteRGB::from(palette))
+        },
+        DitheringAlgorithm::FloydSteinberg => dithering_error_diffusion(img, &palette_colors, metric, FLOYD_STEINBERG_KERNEL, serpentine),
+        DitheringAlgorithm::Atkinson => dithering_error_diffusion(img, &palette_colors, metric, ATKINSON_KERNEL, serpentine),
+        DitheringAlgorithm::JarvisJudiceNinke => dithering_error_diffusion(img, &palette_colors, metric, JARVIS_JUDICE_NINKE_KERNEL, serpentine),
+        DitheringAlgorithm::Stucki => dithering_error_diffusion(img, &palette_colors, metric, STUCKI_KERNEL, serpentine),
+    };
+    Ok(dithered_img)
+}
+
+#[cfg(test)]
+mod test_dithering {
+    use ditherum::color::ColorRGB;
+    use image::{
+        Rgb,
+        RgbImage
+    };
+
+    use crate::{
+        colors::ColorDistanceMetric,
+        dmc::{
+            Dmc,
+            DmcError,
+            PaletteDmc
+        }
+    };
+    use super::{
+        dither_image,
+        DitheringAlgorithm,
+        DitheringOptions
+    };
+
+    fn black_white_palette() -> PaletteDmc {
+        PaletteDmc(vec![
+            Dmc {
+                code: "DMC 310".to_string(),
+                name: "Black".to_string(),
+                color: ColorRGB([0, 0, 0])
+            },
+            Dmc {
+                code: "DMC B5200".to_string(),
+                name: "Snow White".to_string(),
+                color: ColorRGB([255, 255, 255])
+            },
+        ])
+    }
+
+    const ALL_ALGORITHMS: [DitheringAlgorithm; 6] = [
+        DitheringAlgorithm::Nearest,
+        DitheringAlgorithm::Bayer,
+        DitheringAlgorithm::FloydSteinberg,
+        DitheringAlgorithm::Atkinson,
+        DitheringAlgorithm::JarvisJudiceNinke,
+        DitheringAlgorithm::Stucki,
+    ];
+
+    #[test]
+    fn test_dithering_uses_only_palette_colors() {
+        let img = RgbImage::from_fn(32, 16, |x, y| Rgb([(x * 8) as u8, (y * 16) as u8, 100]));
+        let palette = black_white_palette();
+
+        for algorithm in ALL_ALGORITHMS {
+            for serpentine in [false, true] {
+                let dithered_img = dither_image(img.clone(), &palette, &DitheringOptions { algorithm, serpentine }, ColorDistanceMetric::Rgb).unwrap();
+                assert_eq!(dithered_img.dimensions(), img.dimensions());
+                assert!(dithered_img.pixels().all(|px| px.0 == [0, 0, 0] || px.0 == [255, 255, 255]), "{algorithm:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_dithering_mid_gray_mixes_colors() {
+        let img = RgbImage::from_pixel(32, 32, Rgb([128, 128, 128]));
+        let palette = black_white_palette();
+
+        for algorithm in ALL_ALGORITHMS.into_iter().filter(|algorithm| *algorithm != DitheringAlgorithm::Nearest) {
+            let dithered_img = dither_image(img.clone(), &palette, &DitheringOptions { algorithm, serpentine: true }, ColorDistanceMetric::Rgb).unwrap();
+            let white_count = dithered_img.pixels().filter(|px| px.0 == [255, 255, 255]).count();
+            let white_ratio = white_count as f32 / (32.0 * 32.0);
+            assert!((0.35..0.65).contains(&white_ratio), "{algorithm:?}: {white_ratio}");
+        }
+    }
+
+    #[test]
+    fn test_lab_metric_dithering_uses_only_palette_colors() {
+        let img = RgbImage::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 50]));
+        let palette = black_white_palette();
+        let dithered_img = dither_image(
+            img,
+            &palette,
+            &DitheringOptions::default(),
+            ColorDistanceMetric::DeltaE2000
+        ).unwrap();
+        assert!(dithered_img.pixels().all(|px| px.0 == [0, 0, 0] || px.0 == [255, 255, 255]));
+    }
+
+    #[test]
+    fn test_nearest_has_no_confetti() {
+        let img = RgbImage::from_pixel(16, 16, Rgb([100, 100, 100]));
+        let dithered_img = dither_image(
+            img,
+            &black_white_palette(),
+            &DitheringOptions { algorithm: DitheringAlgorithm::Nearest, serpentine: false },
+            ColorDistanceMetric::DeltaE2000
+        ).unwrap();
+        assert!(dithered_img.pixels().all(|px| px.0 == [0, 0, 0]));
+    }
+
+    #[test]
+    fn test_empty_palette_error() {
+        let result = dither_image(RgbImage::new(4, 4), &PaletteDmc(vec![]), &DitheringOptions::default(), ColorDistanceMetric::Rgb);
+        assert!(matches!(result, Err(DmcError::EmptyPalette)));
+    }
+
+    #[test]
+    fn test_saturated_area_error_does_not_streak() {
+        // Saturated red is out of palette gamut, its error would pile up without limit
+        let img = RgbImage::from_fn(256, 200, |x, y| if y < 120 { Rgb([255, 0, 0]) } else { Rgb([(x / 2) as u8 + 40, 128, 128]) });
+        let mut palette = black_white_palette();
+        palette.0.push(Dmc {
+            code: "DMC 321".to_string(),
+            name: "Red".to_string(),
+            color: ColorRGB([200, 30, 30])
+        });
+
+        let error_diffusion_options = [DitheringAlgorithm::FloydSteinberg, DitheringAlgorithm::JarvisJudiceNinke, DitheringAlgorithm::Stucki]
+            .into_iter()
+            .flat_map(|algorithm| [false, true].map(|serpentine| DitheringOptions { algorithm, serpentine }))
+            // Default options are dithered by ditherum
+            .filter(|dithering_options| *dithering_options != DitheringOptions::default());
+
+        for dithering_options in error_diffusion_options {
+            let dithered_img = dither_image(img.clone(), &palette, &dithering_options, ColorDistanceMetric::Rgb).unwrap();
+            let longest_run = (125..200)
+                .flat_map(|y| {
+                    let row = (0..256).map(|x| dithered_img.get_pixel(x, y).0).collect::<Vec<_>>();
+                    row.chunk_by(|a, b| a == b).map(|run| run.len()).collect::<Vec<_>>()
+                })
+                .max()
+                .unwrap();
+            assert!(longest_run < 64, "{dithering_options:?}: {longest_run}");
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet}, 
-    io::BufReader, 
+    io::{BufReader, BufWriter}, 
     ops::Deref, path::Path
 };
 
@@ -15,7 +15,14 @@ use serde::{
     Serialize
 };
 
-const PALETTE_PATH: &str = "res/palette_DMC.json";
+use crate::{
+    colors::ColorDistanceMetric, 
+    dithering::NearestColorFinder, 
+    mask::CellsMask
+};
+
+/// Bundled DMC colors
+pub const PALETTE_PATH: &str = "res/palette_DMC.json";
 
 #[derive(Debug, thiserror::Error)]
 pub enum DmcError {
@@ -42,6 +49,18 @@ pub enum DmcError {
 
     #[error("ColorNotFound")]
     ColorNotFound,
+
+    #[error("Palette is empty")]
+    EmptyPalette,
+
+    #[error("DMC code not found: {0}")]
+    CodeNotFound(String),
+
+    #[error("Not enough symbols: needed={needed}, available={available}")]
+    NotEnoughSymbols {
+        needed: usize,
+        available: usize
+    },
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -49,11 +68,23 @@ pub struct DmcData {
     pub code: String,
     pub name: String,
     pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PaletteDmcData(pub Vec<DmcData>);
 
+/// Palette file can hold DMC records or bare RGB triplets
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum PaletteFileData {
+    Dmc(PaletteDmcData),
+    Rgb(Vec<[u8; 3]>),
+}
+
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Dmc {
@@ -62,6 +93,12 @@ pub struct Dmc {
     pub color: ColorRGB,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct DmcMatch {
+    pub dmc: Dmc,
+    pub distance: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageDmcLegendRecord {
     pub dmc: Dmc,
@@ -72,6 +109,13 @@ pub struct ImageDmcLegendRecord {
 #[derive(Debug, Clone)]
 pub struct ImageDmcLegend(pub HashMap<ColorRGB, ImageDmcLegendRecord>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrillBagsConfig {
+    pub bag_size: usize,
+    pub spare_percentage: f32,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct PaletteDmc(pub Vec<Dmc>);
 
@@ -83,29 +127,33 @@ impl TryFrom<DmcData> for Dmc {
             return Err(Self::Error::DmcDataCorrupted);
         }
 
-        let color = value.color;
-        if !color.starts_with("#") || color.len() != 7 {
-            return Err(Self::Error::HexColorParseFailed(color));
-        }
-
-        if !color[1..]
-            .chars()
-            .all(|c| c.is_ascii_hexdigit()) {
-                return Err(Self::Error::HexColorParseFailed(color));
-            }
-
         Ok(Dmc {
+            color: parse_hex_color(&value.color)?,
             code: value.code,
-            name: value.name,
-            color: ColorRGB([
-                u8::from_str_radix(&color[1..3], 16)?,
-                u8::from_str_radix(&color[3..5], 16)?,
-                u8::from_str_radix(&color[5..], 16)?,
-            ])
+            name: value.name
         })
     }
 }
 
+/// Parses "#RRGGBB" color
+pub fn parse_hex_color(color: &str) -> Result<ColorRGB, DmcError> {
+    if !color.starts_with("#") || color.len() != 7 {
+        return Err(DmcError::HexColorParseFailed(color.to_string()));
+    }
+
+    if !color[1..]
+        .chars()
+        .all(|c| c.is_ascii_hexdigit()) {
+            return Err(DmcError::HexColorParseFailed(color.to_string()));
+        }
+
+    Ok(ColorRGB([
+        u8::from_str_radix(&color[1..3], 16)?,
+        u8::from_str_radix(&color[3..5], 16)?,
+        u8::from_str_radix(&color[5..], 16)?,
+    ]))
+}
+
 impl TryFrom<PaletteDmcData> for PaletteDmc {
     type Error = DmcError;
 
@@ -137,6 +185,29 @@ impl TryFrom<PaletteDmcData> for PaletteDmc {
     }
 }
 
+impl From<PaletteFileData> for PaletteDmcData {
+    fn from(value: PaletteFileData) -> Self {
+        match value {
+            PaletteFileData::Dmc(dmc_palette_data) => dmc_palette_data,
+            PaletteFileData::Rgb(colors) => PaletteDmcData(
+                colors.into_iter()
+                    .enumerate()
+                    .map(|(idx, color)| {
+                        let colorhash = format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2]);
+                        DmcData {
+                            code: colorhash.clone(),
+                            name: format!("Custom {}", idx + 1),
+                            color: colorhash,
+                            count: None,
+                            symbol: None
+                        }
+                    })
+                    .collect()
+            ),
+        }
+    }
+}
+
 impl From<Dmc> for DmcData {
     fn from(value: Dmc) -> Self {
         let colorhash = format!("#{:02X}{:02X}{:02X}",
@@ -147,7 +218,9 @@ impl From<Dmc> for DmcData {
         Self {
             code: value.code,
             name: value.name,
-            color: colorhash
+            color: colorhash,
+            count: None,
+            symbol: None
         }
     }
 }
@@ -183,21 +256,49 @@ impl PaletteDmc {
     pub fn load_dmc_palette_from<P: AsRef<Path>>(path: P) -> Result<PaletteDmc, DmcError> {
         let file = std::fs::File::open(path)?;
         let file_reader = BufReader::new(file);
-        let dmc_palette_data: PaletteDmcData = serde_json::from_reader(file_reader)?;
-        let dmc_palette = PaletteDmc::try_from(dmc_palette_data)?;
+        let palette_file_data: PaletteFileData = serde_json::from_reader(file_reader)?;
+        let dmc_palette = PaletteDmc::try_from(PaletteDmcData::from(palette_file_data))?;
         Ok(dmc_palette)
     }
 
-    pub fn get_subset_closest_to(self, img_rgb: &RgbImage, max_colors_count: usize) -> Result<Self, DmcError> {
-        let rgb_palette = PaletteRGB::from(&self);
-        let subset_palette = rgb_palette
-            .clone()
-            .try_find_closest_subset_using_image(
-                max_colors_count, 
-                img_rgb
-            )?;
+    /// Saves palette in the same format as loaded, annotated with counts and symbols from legend
+    pub fn save_dmc_palette_to<P: AsRef<Path>>(&self, path: P, dmc_image_legend: &ImageDmcLegend) -> Result<(), DmcError> {
+        let dmc_palette_data = PaletteDmcData(
+            self.iter()
+                .map(|dmc| {
+                    let legend_record = dmc_image_legend.get(&dmc.color);
+                    DmcData {
+                        count: Some(legend_record.map(|record| record.count).unwrap_or(0)),
+                        symbol: legend_record.map(|record| record.symbol.clone()),
+                        ..DmcData::from(dmc.clone())
+                    }
+                })
+                .collect()
+        );
+
+        let file = std::fs::File::create(path)?;
+        let file_writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(file_writer, &dmc_palette_data)?;
+        Ok(())
+    }
 
-        let result_dmc_vec: Option<Vec<Dmc>> = subset_palette.iter()
+    pub fn get_subset_closest_to(self, img_rgb: &RgbImage, max_colors_count: usize, metric: ColorDistanceMetric) -> Result<Self, DmcError> {
+        let subset_colors = match metric {
+            ColorDistanceMetric::Rgb => {
+                let rgb_palette = PaletteRGB::from(&self);
+                rgb_palette
+                    .try_find_closest_subset_using_image(
+                        max_colors_count, 
+                        img_rgb
+                    )?
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>()
+            },
+            _ => self.get_clustered_colors(img_rgb, max_colors_count, metric)?,
+        };
+
+        let result_dmc_vec: Option<Vec<Dmc>> = subset_colors.iter()
             .map(|color| {
                 //find in DMC record 
                 self.find_color_dmc(*color)
@@ -209,55 +310,221 @@ impl PaletteDmc {
         Ok(Self(result_dmc_vec))
     }
 
+    /// Palette colors nearest to image pixels, clustered down to `max_colors_count` with k-medoids
+    fn get_clustered_colors(&self, img_rgb: &RgbImage, max_colors_count: usize, metric: ColorDistanceMetric) -> Result<Vec<ColorRGB>, DmcError> {
+        if self.is_empty() {
+            return Err(DmcError::EmptyPalette);
+        }
+
+        let palette_colors = self.iter()
+            .map(|dmc| dmc.color)
+            .collect::<Vec<_>>();
+        let mut finder = NearestColorFinder::new(&palette_colors, metric);
+
+        let mut colors_usage: HashMap<ColorRGB, usize> = HashMap::new();
+        img_rgb.pixels().for_each(|px| {
+            let nearest_color = finder.find(px.0.map(|channel| channel as f32));
+            *colors_usage.entry(nearest_color).or_insert(0) += 1;
+        });
+
+        let mut colors_usage = colors_usage.into_iter().collect::<Vec<_>>();
+        colors_usage.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut medoids = pick_spread_colors(&colors_usage, max_colors_count, metric);
+        for _ in 0..MEDOIDS_MAX_ITERATIONS {
+            let updated_medoids = update_medoids(&colors_usage, &medoids, metric);
+            if updated_medoids == medoids {
+                break;
+            }
+            medoids = updated_medoids;
+        }
+
+        Ok(medoids.into_iter()
+            .map(|idx| colors_usage[idx].0)
+            .collect())
+    }
+
+    /// Keeps only colors present in the image, order is preserved
+    pub fn get_used_subset(&self, colors_counts: &HashMap<ColorRGB, usize>) -> Self {
+        Self(self.iter()
+            .filter(|dmc| colors_counts.contains_key(&dmc.color))
+            .cloned()
+            .collect())
+    }
+
     pub fn find_color_dmc(&self, color: ColorRGB) -> Option<Dmc> {
         let index = self.0.iter().position(|dmc| dmc.color == color)?;
         self.get(index).cloned()
     }
+
+    pub fn find_nearest_dmc(&self, color: ColorRGB, metric: ColorDistanceMetric) -> Option<DmcMatch> {
+        self.find_k_nearest_dmc(color, metric, 1)
+            .into_iter()
+            .next()
+    }
+
+    /// Up to `k` closest DMC, nearest first. Queried color is the reference of asymmetric metrics
+    pub fn find_k_nearest_dmc(&self, color: ColorRGB, metric: ColorDistanceMetric, k: usize) -> Vec<DmcMatch> {
+        let mut matches = self.iter()
+            .map(|dmc| DmcMatch {
+                dmc: dmc.clone(),
+                distance: metric.distance(&color, &dmc.color)
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| a.distance.total_cmp(&b.distance).then_with(|| a.dmc.code.cmp(&b.dmc.code)));
+        matches.truncate(k);
+        matches
+    }
+
+    /// Accepts full code like "DMC 310" or just "310"
+    pub fn find_code_dmc(&self, code: &str) -> Option<Dmc> {
+        let code = code.trim();
+        self.iter()
+            .find(|dmc| {
+                dmc.code.eq_ignore_ascii_case(code)
+                    || dmc.code
+                        .strip_prefix("DMC ")
+                        .is_some_and(|short_code| short_code.eq_ignore_ascii_case(code))
+            })
+            .cloned()
+    }
+}
+
+const MEDOIDS_MAX_ITERATIONS: usize = 10;
+
+/// Most used color first, then ones furthest from already picked. Squared distance is weighted
+/// by square root of pixels count, so small distinct areas like eye highlights or dark outlines
+/// win over large areas of shades close to picked colors
+fn pick_spread_colors(colors_usage: &[(ColorRGB, usize)], count: usize, metric: ColorDistanceMetric) -> Vec<usize> {
+    let mut picked = Vec::new();
+    let mut nearest_distances = vec![f32::INFINITY; colors_usage.len()];
+    let mut next_idx = 0;
+
+    while picked.len() < count.min(colors_usage.len()) {
+        picked.push(next_idx);
+        let picked_color = colors_usage[next_idx].0;
+        colors_usage.iter()
+            .zip(nearest_distances.iter_mut())
+            .for_each(|((color, _), distance)| *distance = distance.min(metric.distance(color, &picked_color)));
+
+        next_idx = colors_usage.iter()
+            .zip(&nearest_distances)
+            .map(|((_, pixels_count), distance)| distance.powi(2) * (*pixels_count as f32).sqrt())
+            .enumerate()
+            .fold((0, f32::MIN), |best, current| if current.1 > best.1 { current } else { best })
+            .0;
+    }
+    picked
 }
 
+/// Colors join nearest medoid, then every cluster takes member with least pixels weighted distance to others
+fn update_medoids(colors_usage: &[(ColorRGB, usize)], medoids: &[usize], metric: ColorDistanceMetric) -> Vec<usize> {
+    let mut clusters = vec![Vec::new(); medoids.len()];
+    colors_usage.iter()
+        .enumerate()
+        .for_each(|(idx, (color, _))| {
+            let distance_to = |medoid_idx: &usize| metric.distance(color, &colors_usage[*medoid_idx].0);
+            let nearest_cluster = (0..medoids.len())
+                .min_by(|a, b| distance_to(&medoids[*a]).total_cmp(&distance_to(&medoids[*b])))
+                .expect("Medoids not empty");
+            clusters[nearest_cluster].push(idx);
+        });
+
+    clusters.iter()
+        .map(|members| {
+            let cost = |medoid_idx: &usize| members.iter()
+                .map(|idx| colors_usage[*idx].1 as f32 * metric.distance(&colors_usage[*idx].0, &colors_usage[*medoid_idx].0))
+                .sum::<f32>();
+            *members.iter()
+                .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+                .expect("Medoid belongs to its cluster")
+        })
+        .collect()
+}
+
+/// Empty cells of `cells_mask` need no drills and are not counted
 pub fn get_colors_counts(
     dithered_img: &RgbImage, 
+    cells_mask: &CellsMask, 
 ) -> HashMap<ColorRGB, usize> {
     let mut colors_counts: HashMap<ColorRGB, usize> = HashMap::new();
-    dithered_img.enumerate_pixels().for_each(|(_, _, px)| {
-        let color_rgb = ColorRGB::from(*px);
-        colors_counts.entry(color_rgb).and_modify(|count| *count += 1).or_insert(1);
-    });
+    dithered_img.enumerate_pixels()
+        .filter(|(x, y, _)| !cells_mask.is_empty_cell(*x, *y))
+        .for_each(|(_, _, px)| {
+            let color_rgb = ColorRGB::from(*px);
+            colors_counts.entry(color_rgb).and_modify(|count| *count += 1).or_insert(1);
+        });
     colors_counts
 }
 
 impl ImageDmcLegend {
+    /// Colors missing in `colors_counts` are skipped, symbols are assigned in palette order
     pub fn extract_from(
         palette_dmc: &PaletteDmc, 
         colors_counts: &HashMap<ColorRGB, usize>,
         symbols: &[&str]
-    ) -> Self {
+    ) -> Result<Self, DmcError> {
+        let used_dmc = palette_dmc.iter()
+            .filter_map(|dmc| {
+                colors_counts.get(&dmc.color)
+                    .map(|count| (dmc, *count))
+            })
+            .collect::<Vec<_>>();
 
-        // // it can happen if image has less colors than DMC palette
-        // if palette_dmc.len() != colors_histogram.len() {
-        //     return Err(());
-        // }
+        if used_dmc.len() > symbols.len() {
+            return Err(DmcError::NotEnoughSymbols { 
+                needed: used_dmc.len(), 
+                available: symbols.len() 
+            });
+        }
 
-        let result_map: Option<HashMap<ColorRGB, ImageDmcLegendRecord>> = palette_dmc.iter()
-            .enumerate()
-            .map(|(idx, dmc)| {
-                colors_counts.get(&dmc.color)
-                    .map(|count| {
-                        (
-                            dmc.color, 
-                            ImageDmcLegendRecord {
-                                dmc: dmc.clone(),
-                                count: *count,
-                                symbol: symbols[idx].to_string()
-                            }
-                        )
-                    })
-                    
-                })
+        let result_map = used_dmc.into_iter()
+            .zip(symbols)
+            .map(|((dmc, count), symbol)| {
+                (
+                    dmc.color, 
+                    ImageDmcLegendRecord {
+                        dmc: dmc.clone(),
+                        count,
+                        symbol: symbol.to_string()
+                    }
+                )
+            })
             .collect();
 
-        let result_map= result_map.unwrap(); //uhh do it better
-        ImageDmcLegend(result_map)
+        Ok(ImageDmcLegend(result_map))
+    }
+}
+
+impl ImageDmcLegend {
+    /// Most used colors first
+    pub fn get_sorted_records(&self) -> Vec<&ImageDmcLegendRecord> {
+        let mut records = self.values().collect::<Vec<_>>();
+        records.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.dmc.code.cmp(&b.dmc.code)));
+        records
+    }
+
+    pub fn get_total_count(&self) -> usize {
+        self.values().map(|record| record.count).sum()
+    }
+}
+
+impl Default for DrillBagsConfig {
+    fn default() -> Self {
+        Self {
+            bag_size: 200,
+            spare_percentage: 10.0
+        }
+    }
+}
+
+impl DrillBagsConfig {
+    pub fn get_bags_count(&self, drills_count: usize) -> usize {
+        if drills_count == 0 {
+            return 0;
+        }
+        let drills_with_spare = (drills_count as f32 * (1.0 + self.spare_percentage.max(0.0) / 100.0)).ceil() as usize;
+        drills_with_spare.div_ceil(self.bag_size.max(1))
     }
 }
 
@@ -278,6 +545,17 @@ fn test_loading_dmc_palette() {
     assert!(!palette.is_empty());
 }
 
+#[test]
+fn test_loading_rgb_palette() {
+    let palette = PaletteDmc::load_dmc_palette_from("res/pink_8_colors.json");
+    assert!(palette.is_ok());
+
+    let palette = palette.unwrap();
+    assert_eq!(palette.len(), 8);
+    assert_eq!(palette[0].color, ColorRGB([38, 26, 27]));
+    assert_eq!(palette[0].code, "#261A1B");
+}
+
 #[test]
 fn test_finding_closest_dmc_1_color_image() {
     let one_color_iamge = image::RgbImage::new(20, 20);
@@ -285,7 +563,7 @@ fn test_finding_closest_dmc_1_color_image() {
 
     let palette = PaletteDmc::load_dmc_palette().unwrap();
 
-    let closest_palette: Result<PaletteDmc, DmcError> = palette.get_subset_closest_to(&one_color_iamge, expected_colors_count);
+    let closest_palette: Result<PaletteDmc, DmcError> = palette.get_subset_closest_to(&one_color_iamge, expected_colors_count, ColorDistanceMetric::Rgb);
     assert!(closest_palette.is_ok());
 
     let closest_palette = closest_palette.unwrap();
@@ -301,7 +579,7 @@ fn test_finding_closest_dmc_not_enough_colors() {
 
     let palette = PaletteDmc::load_dmc_palette().unwrap();
 
-    let closest_palette: Result<PaletteDmc, DmcError> = palette.get_subset_closest_to(&one_color_iamge, requested_colors_count);
+    let closest_palette: Result<PaletteDmc, DmcError> = palette.get_subset_closest_to(&one_color_iamge, requested_colors_count, ColorDistanceMetric::Rgb);
     assert!(closest_palette.is_ok());
 
     let closest_palette = closest_palette.unwrap();
@@ -309,6 +587,201 @@ fn test_finding_closest_dmc_not_enough_colors() {
 }
 
 #[test]
+fn test_drill_bags_count() {
+    let bags_config = DrillBagsConfig {
+        bag_size: 200,
+        spare_percentage: 10.0
+    };
+    assert_eq!(bags_config.get_bags_count(0), 0);
+    assert_eq!(bags_config.get_bags_count(1), 1);
+    assert_eq!(bags_config.get_bags_count(181), 1);
+    assert_eq!(bags_config.get_bags_count(182), 2);
+    assert_eq!(bags_config.get_bags_count(1000), 6);
+}
+
+#[test]
+fn test_saved_dmc_palette_reloads_same() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    let palette = PaletteDmc(palette.iter().take(3).cloned().collect());
+    let colors_counts = HashMap::from([
+        (palette[0].color, 10),
+        (palette[1].color, 20),
+        (palette[2].color, 30),
+    ]);
+    let dmc_image_legend = ImageDmcLegend::extract_from(&palette, &colors_counts, &["A", "B", "C"]).unwrap();
+
+    let path = std::env::temp_dir().join("test_saved_dmc_palette_reloads_same.json");
+    palette.save_dmc_palette_to(&path, &dmc_image_legend).unwrap();
+
+    let reloaded_palette = PaletteDmc::load_dmc_palette_from(&path).unwrap();
+    assert_eq!(palette, reloaded_palette);
+
+    let file = std::fs::File::open(&path).unwrap();
+    let reloaded_data: PaletteDmcData = serde_json::from_reader(BufReader::new(file)).unwrap();
+    assert_eq!(reloaded_data.0[1].count, Some(20));
+    assert_eq!(reloaded_data.0[1].symbol, Some("B".to_string()));
+}
+
+#[test]
+fn test_finding_closest_dmc_lab_metric() {
+    let img = image::RgbImage::from_fn(20, 20, |x, _| {
+        if x < 10 { image::Rgb([0, 0, 0]) } else { image::Rgb([255, 255, 255]) }
+    });
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+
+    let closest_palette = palette.get_subset_closest_to(&img, 4, ColorDistanceMetric::DeltaE2000);
+    assert!(closest_palette.is_ok());
+
+    let closest_palette = closest_palette.unwrap();
+    assert_eq!(closest_palette.len(), 2);
+    assert!(closest_palette.iter().any(|dmc| dmc.code == "DMC 310"));
+    assert!(closest_palette.iter().any(|dmc| dmc.code == "DMC B5200"));
+}
+
+#[test]
+fn test_finding_closest_dmc_nearest_for_lab_metrics() {
+    let quadrant_colors = [
+        ColorRGB([0, 0, 0]),
+        ColorRGB([255, 255, 255]),
+        ColorRGB([200, 0, 0]),
+        ColorRGB([0, 0, 200]),
+    ];
+    let img = image::RgbImage::from_fn(20, 20, |x, y| {
+        image::Rgb(quadrant_colors[((x / 10) + 2 * (y / 10)) as usize].0)
+    });
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+
+    for metric in [ColorDistanceMetric::DeltaE76, ColorDistanceMetric::DeltaE94, ColorDistanceMetric::DeltaE2000] {
+        let closest_palette = palette.clone().get_subset_closest_to(&img, 4, metric).unwrap();
+        assert_eq!(closest_palette.len(), 4, "{metric:?}");
+        quadrant_colors.iter().for_each(|color| {
+            let nearest_dmc = palette.find_nearest_dmc(*color, metric).unwrap().dmc;
+            assert!(closest_palette.contains(&nearest_dmc), "{metric:?}: {color:?}");
+        });
+    }
+}
+
+#[test]
+fn test_finding_closest_dmc_lab_metric_keeps_rare_distinct_color() {
+    // Large black and dark gray areas, white and small red highlight
+    let img = image::RgbImage::from_fn(20, 20, |x, y| match y * 20 + x {
+        0..150 => image::Rgb([0, 0, 0]),
+        150..300 => image::Rgb([50, 50, 50]),
+        300..390 => image::Rgb([255, 255, 255]),
+        _ => image::Rgb([220, 30, 30]),
+    });
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+
+    for metric in [ColorDistanceMetric::DeltaE76, ColorDistanceMetric::DeltaE94, ColorDistanceMetric::DeltaE2000] {
+        let closest_palette = palette.clone().get_subset_closest_to(&img, 3, metric).unwrap();
+        assert_eq!(closest_palette.len(), 3, "{metric:?}");
+        for code in ["DMC 310", "DMC B5200", "DMC 666"] {
+            assert!(closest_palette.iter().any(|dmc| dmc.code == code), "{metric:?}: {code}");
+        }
+    }
+}
+
+#[test]
+fn test_legend_skips_unused_colors() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    let palette = PaletteDmc(palette.iter().take(3).cloned().collect());
+    let colors_counts = HashMap::from([
+        (palette[0].color, 10),
+        (palette[2].color, 30),
+    ]);
+
+    let dmc_image_legend = ImageDmcLegend::extract_from(&palette, &colors_counts, &["A", "B", "C"]);
+    assert!(dmc_image_legend.is_ok());
+
+    let dmc_image_legend = dmc_image_legend.unwrap();
+    assert_eq!(dmc_image_legend.len(), 2);
+    assert_eq!(dmc_image_legend.get(&palette[2].color).unwrap().symbol, "B");
+    assert!(dmc_image_legend.get(&palette[1].color).is_none());
+
+    assert_eq!(palette.get_used_subset(&colors_counts).len(), 2);
+}
+
+#[test]
+fn test_legend_not_enough_symbols() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    let palette = PaletteDmc(palette.iter().take(3).cloned().collect());
+    let colors_counts = palette.iter()
+        .map(|dmc| (dmc.color, 1))
+        .collect::<HashMap<_, _>>();
+
+    let dmc_image_legend = ImageDmcLegend::extract_from(&palette, &colors_counts, &["A", "B"]);
+    assert!(matches!(dmc_image_legend, Err(DmcError::NotEnoughSymbols { needed: 3, available: 2 })));
+}
+
+#[test]
+fn test_legend_large_palette() {
+    let palette = PaletteDmc::load_dmc_palette_from("res/palette_test_125_colors.json").unwrap();
+    let palette = PaletteDmc(palette.iter().take(100).cloned().collect());
+    let colors_counts = palette.iter()
+        .map(|dmc| (dmc.color, 1))
+        .collect::<HashMap<_, _>>();
+
+    let symbols = crate::symbols::generate_label_symbols(palette.len());
+    let dmc_image_legend = ImageDmcLegend::extract_from(&palette, &colors_counts, &symbols);
+    assert_eq!(dmc_image_legend.unwrap().len(), 100);
+}
+
+#[test]
+fn test_find_nearest_dmc() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+
+    let exact = palette.find_nearest_dmc(ColorRGB([0, 0, 0]), ColorDistanceMetric::DeltaE2000);
+    assert!(exact.is_some());
+    let exact = exact.unwrap();
+    assert_eq!(exact.dmc.code, "DMC 310");
+    assert_eq!(exact.distance, 0.0);
+
+    let near_black = palette.find_nearest_dmc(ColorRGB([5, 3, 4]), ColorDistanceMetric::Rgb);
+    assert_eq!(near_black.unwrap().dmc.code, "DMC 310");
+}
+
+#[test]
+fn test_find_nearest_dmc_delta_e94_reference() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    let color = ColorRGB([40, 60, 200]);
+
+    // Chroma is weighted by queried color, DMC 797 would win with swapped arguments
+    let nearest = palette.find_nearest_dmc(color, ColorDistanceMetric::DeltaE94).unwrap();
+    assert_eq!(nearest.dmc.code, "DMC 3837");
+    assert_eq!(nearest.distance, ColorDistanceMetric::DeltaE94.distance(&color, &nearest.dmc.color));
+}
+
+#[test]
+fn test_find_k_nearest_dmc_sorted() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    let k = 5;
+
+    let matches = palette.find_k_nearest_dmc(ColorRGB([200, 120, 140]), ColorDistanceMetric::DeltaE2000, k);
+    assert_eq!(matches.len(), k);
+    assert!(matches.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+
+    let empty_palette = PaletteDmc(vec![]);
+    assert!(empty_palette.find_nearest_dmc(ColorRGB([0, 0, 0]), ColorDistanceMetric::Rgb).is_none());
+}
+
+#[test]
+fn test_find_code_dmc() {
+    let palette = PaletteDmc::load_dmc_palette().unwrap();
+    assert_eq!(palette.find_code_dmc("310").map(|dmc| dmc.color), Some(ColorRGB([0, 0, 0])));
+    assert_eq!(palette.find_code_dmc("dmc b5200").map(|dmc| dmc.color), Some(ColorRGB([255, 255, 255])));
+    assert!(palette.find_code_dmc("nope").is_none());
+}
+
+#[test]
+fn test_parse_hex_color() {
+    assert_eq!(parse_hex_color("#FF000F").unwrap(), ColorRGB([255, 0, 15]));
+    assert!(parse_hex_color("FF000F").is_err());
+    assert!(parse_hex_color("#FF00").is_err());
+    assert!(parse_hex_color("#GG0000").is_err());
+}
+
+#[test]
+#[allow(clippy::useless_format)]
 fn test_dmc_to_dmcdata_convertion() {
     let dmc = Dmc{
         code: format!("DX123"),
@@ -324,6 +797,7 @@ fn test_dmc_to_dmcdata_convertion() {
 }
 
 #[test]
+#[allow(clippy::useless_format)]
 fn test_palettedmc_convertions() {
     let dmc1 = Dmc{
         code: format!("DX123"),
@@ -1,35 +1,93 @@
 use std::path::Path;
 
 use ditherum::{
-    algorithms::dithering::dithering_floyd_steinberg_rgb, 
-    image::manip::rgb_image_reshape, 
+    color::ColorRGB,
     palette::{errors::PaletteError, PaletteRGB}
 };
 
-use image::{ImageError, RgbImage};
+use image::{imageops, GrayImage, ImageError, Luma, Rgb, RgbImage};
+use serde::{
+    Deserialize, 
+    Serialize
+};
 
 use crate::{
+    adjustments::{
+        ImageAdjustments, 
+        ImageAdjustmentsError
+    }, 
+    colors::ColorDistanceMetric, 
+    config::{
+        GenerationConfig, 
+        GenerationConfigError
+    }, 
+    confetti::reduce_confetti, 
+    dithering::dither_image, 
     dmc::{
-        get_colors_counts, DmcError, ImageDmcLegend, PaletteDmc
+        get_colors_counts, Dmc, DmcError, ImageDmcLegend, PaletteDmc
+    }, 
+    mask::{
+        load_mask, 
+        CellsMask, 
+        MaskedCells
+    }, 
+    preview::{
+        render_realistic_preview, 
+        PreviewOptions
+    }, 
+    render::{
+        render_diamond_painting_project, 
+        PageLayout, 
+        PatternOutput, 
+        RenderOptions
+    }, 
+    style::{
+        RenderStyle, 
+        RenderStyleError
+    }, 
+    sizing::{
+        crop_image_region, 
+        fit_image_on_canvas_size, 
+        AspectFit, 
+        CanvasSizing, 
+        FittedImage
+    }, 
+    symbols::{
+        generate_label_symbols, 
+        get_label_symbols_max_count
+    }, 
+    tiling::{
+        orient_paper_sheet_for_tiling, 
+        reserve_grid_rulers_area, 
+        TileLayout
     }, 
-    render::render_diamond_painting_project, 
     types::{
         DiamondShape, 
         PaperSheet, 
+        Rect2U, 
         Size2F, 
         Size2U
     }
 };
 
-const LABEL_SYMBOLS: [&str; 32] = [
-    "1", "2", "4", "5", "7", "9",
-    "A", "B", "C", "W", "X", "S", "R",
-    "a", "i", "m", "h", "r",
-    "c", "u", "z", "q", "Q", "8", "Y", "+", "=", "@", "#", "$", "%", "*"
-];
-// "★", "✦", "❖", "⌖", "▲", "⬠", "⊙", "☾", "⌘", "✪", "♡", "♞", "♠", "♛",
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaletteSelection {
+    /// Pick subset of provided palette closest to image colors
+    #[default]
+    ClosestSubset,
+    /// Use provided palette as is, e.g. drills already owned.
+    /// Colors not used by image stay in summary and saved palette, with zero count
+    Fixed,
+}
 
-const PALLETE_LEN_MAX: usize = LABEL_SYMBOLS.len();
+#[derive(Debug, Clone)]
+pub struct ProcessSummary {
+    pub dmc_palette: PaletteDmc,
+    pub confetti_cells_changed: usize,
+    /// Cells left without drills by mask
+    pub empty_cells_count: usize,
+}
 
 #[derive(Debug, Clone)]
 pub struct PreprocessResult {
@@ -52,14 +110,33 @@ pub enum ProcessError {
     #[error("IoError, reason={0}")]
     IoError(#[from] std::io::Error),
 
-    #[error("BadColorsCount: expected={expected}, possible={possible}")]
-    BadColorsCount {
-        expected: usize,
-        possible: usize
+    #[error("NoCoveredCells: mask leaves no cell for drills")]
+    NoCoveredCells,
+
+    #[error("PaletteTooLarge: len={len}, max={max}")]
+    PaletteTooLarge {
+        len: usize,
+        max: usize
     },
+
+    #[error("RenderStyleError, reason={0}")]
+    RenderStyleError(#[from] RenderStyleError),
+
+    #[error("ImageAdjustmentsError, reason={0}")]
+    ImageAdjustmentsError(#[from] ImageAdjustmentsError),
+
+    #[error("GenerationConfigError, reason={0}")]
+    GenerationConfigError(#[from] GenerationConfigError),
 }
 
-fn fit_image_on_paper_printable_area(mut paper_sheet: PaperSheet, diamond_shape: &DiamondShape, rgb_img: RgbImage) -> (PaperSheet, RgbImage) {
+fn fit_image_on_paper_printable_area(
+    mut paper_sheet: PaperSheet, 
+    diamond_shape: &DiamondShape, 
+    canvas_sizing: &CanvasSizing, 
+    border_color: Rgb<u8>, 
+    rgb_img: RgbImage, 
+    mask: Option<GrayImage>
+) -> (PaperSheet, FittedImage) {
     let rgb_img_is_vertical = Size2F {
         w: rgb_img.width() as f32,
         h: rgb_img.height() as f32
@@ -70,14 +147,130 @@ fn fit_image_on_paper_printable_area(mut paper_sheet: PaperSheet, diamond_shape:
         paper_sheet.change_orientation();
     }
 
-    let expected_width_in_pixels = (paper_sheet.get_printing_area_rect().size.w / diamond_shape.get_size()).round() as u32;
-    let result_img = rgb_image_reshape(
+    let printing_area_size = paper_sheet.get_printing_area_rect().size;
+    let printing_area_cells = Size2U {
+        w: ((printing_area_size.w / diamond_shape.get_size()).floor() as u32).max(1),
+        h: ((printing_area_size.h / diamond_shape.get_size()).floor() as u32).max(1),
+    };
+    let fitted_image = fit_image_on_canvas_size(
         rgb_img, 
-        Some(expected_width_in_pixels), 
-        None
+        mask, 
+        printing_area_cells, 
+        canvas_sizing.aspect_fit.resolve(&canvas_sizing.policy), 
+        canvas_sizing.resampling_filter, 
+        canvas_sizing.focal_point.unwrap_or((0.5, 0.5)), 
+        border_color
+    );
+
+    (paper_sheet, fitted_image)
+}
+
+/// Canvas not fitting on single page is split on many pages
+fn fit_image_on_canvas(
+    paper_sheet: PaperSheet, 
+    diamond_shape: &DiamondShape, 
+    render_style: &RenderStyle, 
+    canvas_size: Size2U, 
+    canvas_sizing: &CanvasSizing, 
+    border_color: Rgb<u8>, 
+    rgb_img: RgbImage, 
+    mask: Option<GrayImage>
+) -> (PaperSheet, FittedImage, PageLayout) {
+    let fitted_image = fit_image_on_canvas_size(
+        rgb_img, 
+        mask, 
+        canvas_size, 
+        canvas_sizing.aspect_fit.resolve(&canvas_sizing.policy), 
+        canvas_sizing.resampling_filter, 
+        canvas_sizing.focal_point.unwrap_or((0.5, 0.5)), 
+        border_color
+    );
+    let canvas_size = Size2U { w: fitted_image.img.width(), h: fitted_image.img.height() };
+
+    let paper_sheet = orient_paper_sheet_for_tiling(
+        paper_sheet, 
+        diamond_shape, 
+        render_style, 
+        canvas_size, 
+        canvas_sizing.overlap_cells
+    );
+
+    let pages_count = TileLayout::for_paper_sheet(&paper_sheet, diamond_shape, render_style, canvas_size, canvas_sizing.overlap_cells).pages_count();
+    let page_layout = match pages_count {
+        1 => PageLayout::SinglePage,
+        _ => PageLayout::Tiled { overlap_cells: canvas_sizing.overlap_cells },
+    };
+
+    (paper_sheet, fitted_image, page_layout)
+}
+
+/// Only image content is adjusted, letterbox border keeps its color
+fn apply_image_adjustments(mut fitted_image: FittedImage, image_adjustments: &ImageAdjustments) -> FittedImage {
+    if image_adjustments.is_empty() {
+        return fitted_image;
+    }
+
+    let content_cells = &fitted_image.content_cells;
+    let content_img = imageops::crop_imm(
+        &fitted_image.img, 
+        content_cells.pos.x, 
+        content_cells.pos.y, 
+        content_cells.size.w, 
+        content_cells.size.h
+    ).to_image();
+    imageops::replace(
+        &mut fitted_image.img, 
+        &image_adjustments.apply(content_img), 
+        content_cells.pos.x as i64, 
+        content_cells.pos.y as i64
     );
+    fitted_image
+}
+
+/// Letterbox border cells get exact border color instead of dithered one
+fn paint_letterbox_border(dithered_img: &mut RgbImage, content_cells: &Rect2U, border_color: Rgb<u8>) {
+    for (x, y, pixel) in dithered_img.enumerate_pixels_mut() {
+        if !content_cells.contains(x, y) {
+            *pixel = border_color;
+        }
+    }
+}
+
+/// Masked out cells covered with background drills
+fn paint_masked_cells(img: &mut RgbImage, masked_cells: &CellsMask, background_color: Rgb<u8>) {
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        if masked_cells.is_empty_cell(x, y) {
+            *pixel = background_color;
+        }
+    }
+}
+
+/// Empty cells get flat palette color closest to average covered color,
+/// so they carry no dithering error and content edge is not darkened by transparent pixels
+fn fill_empty_cells(
+    img: &mut RgbImage,
+    cells_mask: &CellsMask,
+    palette: &PaletteDmc,
+    metric: ColorDistanceMetric
+) {
+    let covered_pixels = cells_mask.get_covered_pixels(img);
+    let covered_count = covered_pixels.pixels().len().max(1) as u64;
+    let sums = covered_pixels.pixels()
+        .fold([0u64; 3], |sums, px| [0, 1, 2].map(|idx| sums[idx] + px.0[idx] as u64));
+    let average_color = ColorRGB(sums.map(|sum| (sum / covered_count) as u8));
+    let Some(fill_dmc) = palette.find_nearest_dmc(average_color, metric) else {
+        return;
+    };
+    paint_masked_cells(img, cells_mask, Rgb(fill_dmc.dmc.color.0));
+}
 
-    (paper_sheet, result_img)
+/// Cells painted with exact color after dithering, marked as empty
+fn get_fixed_cells(masked_cells: &CellsMask, letterbox_cells: Option<&Rect2U>, size: Size2U) -> CellsMask {
+    let coverage = GrayImage::from_fn(size.w, size.h, |x, y| {
+        let is_letterbox_cell = letterbox_cells.is_some_and(|content_cells| !content_cells.contains(x, y));
+        if is_letterbox_cell || masked_cells.is_empty_cell(x, y) { Luma([0]) } else { Luma([255]) }
+    });
+    CellsMask::from_coverage(&coverage)
 }
 
 pub fn extract_palette_subset<P: AsRef<Path>> (
@@ -87,18 +280,27 @@ pub fn extract_palette_subset<P: AsRef<Path>> (
     diamond_shape: DiamondShape,
     image_path: P
 ) -> Result<PaletteDmc, ProcessError> {
-    let max_colors_count = max_colors_count.min(PALLETE_LEN_MAX);
+    let max_colors_count = max_colors_count.min(get_label_symbols_max_count());
 
     // Fit image to printable area
     let img_rgb = image::open(image_path)?
         .to_rgb8();
 
-    let (_, img_rgb) = fit_image_on_paper_printable_area(paper_sheet, &diamond_shape, img_rgb);
+    let (_, fitted_image) = fit_image_on_paper_printable_area(
+        paper_sheet, 
+        &diamond_shape, 
+        &CanvasSizing::default(), 
+        Rgb([255, 255, 255]), 
+        img_rgb, 
+        None
+    );
     
-    let dmc_subset_palette = provided_dmc_palette.get_subset_closest_to(&img_rgb, max_colors_count)?;
+    let dmc_subset_palette = provided_dmc_palette.get_subset_closest_to(&fitted_image.img, max_colors_count, ColorDistanceMetric::default())?;
     Ok(dmc_subset_palette)
 }
 
+/// Original entry point, options added later take their `GenerationConfig` defaults
+#[deprecated(note = "use `process_image_with_config` with `GenerationConfig::builder`")]
 pub fn process_image_with_path<P: AsRef<Path>> (
     paper_sheet: PaperSheet,
     provided_dmc_palette: PaletteDmc,
@@ -109,74 +311,298 @@ pub fn process_image_with_path<P: AsRef<Path>> (
     dmc_palette_path: Option<P>,
     output_path: &str,
 ) -> Result<PaletteDmc, ProcessError> {
-    let max_colors_count = max_colors_count.min(PALLETE_LEN_MAX);
+    let mut config_builder = GenerationConfig::builder(image_path.as_ref())
+        .paper_sheet(paper_sheet)
+        .diamond_shape(diamond_shape)
+        .max_colors(max_colors_count)
+        .outputs(vec![PatternOutput::pdf(output_path)]);
+    if let Some(path) = preview_path {
+        config_builder = config_builder.preview(path.as_ref(), PreviewOptions::default());
+    }
+    if let Some(path) = dmc_palette_path {
+        config_builder = config_builder.dmc_palette_path(path.as_ref());
+    }
 
-    // Fit image to printable area
-    let img_rgb = image::open(image_path)?
-        .to_rgb8();
-    let (paper_sheet, img_rgb) = fit_image_on_paper_printable_area(
-        paper_sheet, 
-        &diamond_shape, 
-        img_rgb
-    );
-    
-    let dmc_subset_palette = provided_dmc_palette.get_subset_closest_to(&img_rgb, max_colors_count)?;
+    let summary = process_image_with_palette(&config_builder.build(), provided_dmc_palette)?;
+    Ok(summary.dmc_palette)
+}
 
-    let dithered_img = dithering_floyd_steinberg_rgb(
-        img_rgb, 
-        PaletteRGB::from(&dmc_subset_palette)
-    );
+/// Whole job described by config, palette is loaded from config palette path
+pub fn process_image_with_config(config: &GenerationConfig) -> Result<ProcessSummary, ProcessError> {
+    let provided_dmc_palette = PaletteDmc::load_dmc_palette_from(&config.palette.path)?;
+    process_image_with_palette(config, provided_dmc_palette)
+}
+
+/// Config palette path is ignored, `provided_dmc_palette` is used instead
+fn process_image_with_palette(config: &GenerationConfig, provided_dmc_palette: PaletteDmc) -> Result<ProcessSummary, ProcessError> {
+    let GenerationConfig {
+        image_path,
+        paper_sheet,
+        diamond_shape,
+        palette,
+        color_distance_metric,
+        dithering: dithering_options,
+        confetti_reduction,
+        adjustments: image_adjustments,
+        mask: mask_options,
+        canvas_sizing,
+        drill_bags: drill_bags_config,
+        pattern_pages,
+        style: render_style,
+        grid: draw_grid_lines,
+        outputs,
+        preview_path,
+        preview: preview_options,
+        dmc_palette_path,
+    } = config.clone();
+    let max_colors_count = palette.max_colors.min(get_label_symbols_max_count());
+
+    let paper_sheet = if draw_grid_lines || pattern_pages.has_grid_lines() {
+        reserve_grid_rulers_area(paper_sheet, &render_style)
+    } else {
+        paper_sheet
+    };
+
+    // Border and background colors come from provided palette, even if not picked for image
+    let find_code_dmc = |code: &String| provided_dmc_palette.find_code_dmc(code)
+        .ok_or(DmcError::CodeNotFound(code.clone()));
+
+    // Border is drawn with exact DMC color only when letterboxing
+    let letterbox_border_dmc = match canvas_sizing.aspect_fit.resolve(&canvas_sizing.policy) {
+        AspectFit::Letterbox => canvas_sizing.border_dmc_code.as_ref()
+            .map(find_code_dmc)
+            .transpose()?,
+        _ => None,
+    };
+    let mask_background_dmc = match &mask_options.masked_cells {
+        MaskedCells::Background(code) => Some(find_code_dmc(code)?),
+        MaskedCells::Empty => None,
+    };
+    let border_color = letterbox_border_dmc.as_ref()
+        .map(|dmc| Rgb(dmc.color.0))
+        .unwrap_or(Rgb([255, 255, 255]));
+
+    // Fit image to printable area or size it by policy, tiled if needed
+    let img = image::open(image_path)?;
+    let mask = load_mask(&img, &mask_options.source, canvas_sizing.resampling_filter)?;
+    // Sizing and fill see only region chosen by user
+    let img_rgb = crop_image_region(img.to_rgb8(), canvas_sizing.crop_region);
+    let mask = mask.map(|mask| crop_image_region(mask, canvas_sizing.crop_region));
+    let image_size = Size2U { w: img_rgb.width(), h: img_rgb.height() };
+    let (paper_sheet, fitted_image, page_layout) = match canvas_sizing.policy.get_canvas_size_in_diamonds(&diamond_shape, image_size) {
+        None => {
+            let (paper_sheet, fitted_image) = fit_image_on_paper_printable_area(
+                paper_sheet, 
+                &diamond_shape, 
+                &canvas_sizing, 
+                border_color, 
+                img_rgb, 
+                mask
+            );
+            (paper_sheet, fitted_image, PageLayout::SinglePage)
+        },
+        Some(canvas_size) => fit_image_on_canvas(
+            paper_sheet, 
+            &diamond_shape, 
+            &render_style, 
+            canvas_size, 
+            &canvas_sizing, 
+            border_color, 
+            img_rgb, 
+            mask
+        ),
+    };
+    let mut fitted_image = apply_image_adjustments(fitted_image, &image_adjustments);
+
+    let masked_cells = fitted_image.mask.as_ref()
+        .map(CellsMask::from_coverage)
+        .unwrap_or_default();
+    // Background cells get drills too, only empty ones are skipped
+    let cells_mask = match &mask_background_dmc {
+        Some(background_dmc) => {
+            paint_masked_cells(&mut fitted_image.img, &masked_cells, Rgb(background_dmc.color.0));
+            CellsMask::default()
+        },
+        None => masked_cells.clone(),
+    };
+    let cells_count = (fitted_image.img.width() * fitted_image.img.height()) as usize;
+    if cells_mask.get_empty_cells_count() == cells_count {
+        return Err(ProcessError::NoCoveredCells);
+    }
+
+    let mut fixed_dmcs: Vec<&Dmc> = Vec::new();
+    for dmc in letterbox_border_dmc.iter().chain(mask_background_dmc.iter()) {
+        if !fixed_dmcs.iter().any(|fixed_dmc| fixed_dmc.code == dmc.code) {
+            fixed_dmcs.push(dmc);
+        }
+    }
     
-    if let Some(path) = preview_path {
-        dithered_img.save(path)?;
+    let mut dmc_subset_palette = match palette.selection {
+        PaletteSelection::ClosestSubset => {
+            // Slots reserved for border and background colors, picked by image content only
+            let subset_colors_count = max_colors_count.saturating_sub(fixed_dmcs.len()).max(1);
+            provided_dmc_palette.get_subset_closest_to(
+                &masked_cells.get_covered_pixels(&fitted_image.img), 
+                subset_colors_count, 
+                color_distance_metric
+            )?
+        },
+        PaletteSelection::Fixed => provided_dmc_palette,
+    };
+
+    // Painted cells must be in legend, colors shared with palette use its DMC
+    for fixed_dmc in fixed_dmcs {
+        if !dmc_subset_palette.iter().any(|dmc| dmc.color == fixed_dmc.color) {
+            dmc_subset_palette.0.push(fixed_dmc.clone());
+        }
     }
 
-    let colors_counts = get_colors_counts(&dithered_img);
+    let palette_len_max = get_label_symbols_max_count();
+    if dmc_subset_palette.len() > palette_len_max {
+        return Err(ProcessError::PaletteTooLarge { len: dmc_subset_palette.len(), max: palette_len_max });
+    }
 
-    if let Some(_path) = dmc_palette_path {
-        println!("Todo use path for palette");
+    if dmc_subset_palette.is_empty() {
+        return Err(DmcError::EmptyPalette.into());
     }
 
-    if dmc_subset_palette.len() != colors_counts.len() {
-        return Err(ProcessError::BadColorsCount {expected: dmc_subset_palette.len(), possible: colors_counts.len()})
+    if mask_background_dmc.is_none() && masked_cells.has_empty_cells() {
+        fill_empty_cells(&mut fitted_image.img, &masked_cells, &dmc_subset_palette, color_distance_metric);
+    }
+
+    let mut dithered_img = dither_image(
+        fitted_image.img, 
+        &dmc_subset_palette, 
+        &dithering_options, 
+        color_distance_metric
+    )?;
+
+    if letterbox_border_dmc.is_some() {
+        paint_letterbox_border(&mut dithered_img, &fitted_image.content_cells, border_color);
+    }
+    if let Some(background_dmc) = &mask_background_dmc {
+        paint_masked_cells(&mut dithered_img, &masked_cells, Rgb(background_dmc.color.0));
+    }
+
+    let confetti_cells_changed = confetti_reduction
+        .map(|confetti_reduction| {
+            let fixed_cells = get_fixed_cells(
+                &masked_cells, 
+                letterbox_border_dmc.as_ref().map(|_| &fitted_image.content_cells), 
+                Size2U { w: dithered_img.width(), h: dithered_img.height() }
+            );
+            reduce_confetti(&mut dithered_img, &fixed_cells, &confetti_reduction, color_distance_metric)
+        })
+        .unwrap_or(0);
+    
+    if let Some(path) = preview_path {
+        render_realistic_preview(&dithered_img, &cells_mask, &diamond_shape, &preview_options).save(path)?;
+    }
+
+    // Some colors may end up unused after dithering, only picked ones are dropped
+    let colors_counts = get_colors_counts(&dithered_img, &cells_mask);
+    let empty_cells_count = cells_mask.get_empty_cells_count();
+    if palette.selection == PaletteSelection::ClosestSubset {
+        dmc_subset_palette = dmc_subset_palette.get_used_subset(&colors_counts);
     }
 
     let dmc_image_legend = ImageDmcLegend::extract_from(
         &dmc_subset_palette, 
         &colors_counts, 
-        &LABEL_SYMBOLS
-    );
-    // println!("{dmc_image_legend:?}");
+        &generate_label_symbols(dmc_subset_palette.len())
+    )?;
 
-    render_diamond_painting_project(
+    if let Some(path) = dmc_palette_path {
+        dmc_subset_palette.save_dmc_palette_to(path, &dmc_image_legend)?;
+    }
+
+    let render_options = RenderOptions {
         paper_sheet,
         diamond_shape,
-        dmc_image_legend,
-        dithered_img,
-        true,
-        output_path
+        page_layout,
+        drill_bags_config,
+        pattern_pages,
+        render_style,
+        draw_grid_lines,
+    };
+    render_diamond_painting_project(
+        &render_options,
+        &dmc_image_legend,
+        &dithered_img,
+        &cells_mask,
+        &outputs
     )?;
 
-    Ok(dmc_subset_palette)
+    Ok(ProcessSummary {
+        dmc_palette: dmc_subset_palette,
+        confetti_cells_changed,
+        empty_cells_count
+    })
 }
 
 #[cfg(test)]
 mod test_generator {
     use std::path::Path;
 
+    use image::{
+        Rgb, 
+        RgbImage, 
+        Rgba, 
+        RgbaImage
+    };
+
     use crate::{
-        dmc::PaletteDmc, 
+        adjustments::{
+            ImageAdjustment, 
+            ImageAdjustments
+        }, 
+        colors::ColorDistanceMetric, 
+        confetti::ConfettiReduction, 
+        config::GenerationConfig, 
+        dmc::{
+            DmcError, 
+            PaletteDmc, 
+            PaletteDmcData, 
+            PALETTE_PATH
+        }, 
         generator::extract_palette_subset, 
+        mask::{
+            MaskOptions, 
+            MaskSource, 
+            MaskedCells
+        }, 
+        preview::PreviewOptions, 
+        render::{
+            OutputFormat, 
+            PatternOutput, 
+            PatternPages
+        }, 
+        sizing::{
+            AspectFit, 
+            CanvasSizing, 
+            FittedImage, 
+            SizingPolicy
+        }, 
+        style::RenderStyle, 
         types::{
             DiamondShape, 
-            PaperSheet
+            PaperSheet, 
+            Pos2U, 
+            Rect2U, 
+            Size2U
         }
     };
     use super::{
-        process_image_with_path, 
-        ProcessError
+        apply_image_adjustments, 
+        process_image_with_config, 
+        PaletteSelection, 
+        ProcessError, 
+        ProcessSummary
     };
+    #[allow(deprecated)]
+    use super::process_image_with_path;
 
+    #[allow(deprecated)]
     fn full_generate_helper(
         paper_sheet: PaperSheet,
         provided_dmc_palette: PaletteDmc,
@@ -200,6 +626,64 @@ mod test_generator {
         )
     }
 
+    /// Outputs are suffixed with `_config`, so they don't clash with `full_generate_helper` ones
+    fn full_generate_config_helper(
+        paper_sheet: PaperSheet,
+        palette_path: &str,
+        palette_selection: PaletteSelection,
+        image_filename: &str,
+        max_colors_count: usize,
+        canvas_sizing: CanvasSizing
+    ) -> Result<ProcessSummary, ProcessError> {
+        let filename_stem = Path::new(image_filename)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(|stem| format!("{stem}_config"))
+            .unwrap();
+
+        let config = GenerationConfig::builder(format!("res/{image_filename}"))
+            .paper_sheet(paper_sheet)
+            .palette_path(palette_path)
+            .palette_selection(palette_selection)
+            .max_colors(max_colors_count)
+            .canvas_sizing(canvas_sizing)
+            .preview(format!("res/outputs/{filename_stem}_preview.png"), PreviewOptions::default())
+            .dmc_palette_path(format!("res/outputs/{filename_stem}_dmc_palette.json"))
+            .outputs(vec![PatternOutput::pdf(&format!("res/outputs/{filename_stem}.pdf"))])
+            .build();
+
+        remove_stale_outputs(&[
+            &format!("res/outputs/{filename_stem}_preview.png"),
+            &format!("res/outputs/{filename_stem}_dmc_palette.json"),
+            &format!("res/outputs/{filename_stem}.pdf"),
+        ]);
+        process_image_with_config(&config)
+    }
+
+    /// Outputs left by earlier runs would pass existence checks
+    fn remove_stale_outputs(output_paths: &[&str]) {
+        for path in output_paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Summary of image without mask, every output written
+    fn assert_summary(summary: &ProcessSummary, max_colors_count: usize, output_paths: &[&str]) {
+        assert!(!summary.dmc_palette.is_empty());
+        assert!(summary.dmc_palette.len() <= max_colors_count, "{} colors", summary.dmc_palette.len());
+        assert_eq!(summary.empty_cells_count, 0);
+        for path in output_paths {
+            assert!(Path::new(path).exists(), "{path}");
+        }
+    }
+
+    fn process_and_assert(config: &GenerationConfig, output_paths: &[&str]) -> ProcessSummary {
+        remove_stale_outputs(output_paths);
+        let summary = process_image_with_config(config).unwrap();
+        assert_summary(&summary, config.palette.max_colors, output_paths);
+        summary
+    }
+
     #[test]
     fn test_process_image_with_path_a4_max_12_colors() {
         let max_colors_count = 12;
@@ -216,6 +700,84 @@ mod test_generator {
         assert!(processing_result.len() <= max_colors_count);
     }
 
+    #[test]
+    fn test_process_image_with_config_a4_max_12_colors() {
+        let max_colors_count = 12;
+        let processing_result = full_generate_config_helper(
+            PaperSheet::standard_a4(),
+            PALETTE_PATH,
+            PaletteSelection::ClosestSubset,
+            "test_pink_300.jpg",
+            max_colors_count,
+            CanvasSizing::default()
+        );
+    
+        assert!(processing_result.is_ok());
+        let processing_result = processing_result.unwrap();
+
+        assert_summary(&processing_result, max_colors_count, &[
+            "res/outputs/test_pink_300_config.pdf",
+            "res/outputs/test_pink_300_config_preview.png",
+        ]);
+
+        let exported_palette = PaletteDmc::load_dmc_palette_from("res/outputs/test_pink_300_config_dmc_palette.json");
+        assert!(exported_palette.is_ok());
+        assert_eq!(exported_palette.unwrap(), processing_result.dmc_palette);
+    }
+
+    #[test]
+    fn test_process_image_with_config_a4_grid_lines() {
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .diamond_shape(DiamondShape::common_square())
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::CanvasDrills(Size2U { w: 120, h: 100 }),
+                overlap_cells: 2,
+                ..Default::default()
+            })
+            .grid(true)
+            .outputs(vec![PatternOutput::pdf("res/outputs/test_pink_300_grid.pdf")])
+            .build();
+
+        process_and_assert(&config, &["res/outputs/test_pink_300_grid.pdf"]);
+    }
+
+    #[test]
+    fn test_process_image_with_config_a4_color_and_symbols_pages() {
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .pattern_pages(PatternPages::ColorAndSymbols)
+            .style(RenderStyle {
+                footer_text: Some("Diamond Shop".to_string()),
+                ..Default::default()
+            })
+            .outputs(vec![PatternOutput::pdf("res/outputs/test_pink_300_symbols.pdf")])
+            .build();
+
+        process_and_assert(&config, &["res/outputs/test_pink_300_symbols.pdf"]);
+    }
+
+    #[test]
+    fn test_process_image_with_config_a4_svg_and_png_outputs() {
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .max_colors(8)
+            .grid(true)
+            .outputs(vec![
+                PatternOutput { format: OutputFormat::Svg, path: "res/outputs/test_pink_300_pattern.svg".to_string() },
+                PatternOutput { format: OutputFormat::Png { dpi: 100.0 }, path: "res/outputs/test_pink_300_pattern.png".to_string() },
+            ])
+            .build();
+
+        // Pattern page followed by legend page
+        process_and_assert(&config, &[
+            "res/outputs/test_pink_300_pattern_1.svg",
+            "res/outputs/test_pink_300_pattern_2.svg",
+            "res/outputs/test_pink_300_pattern_1.png",
+            "res/outputs/test_pink_300_pattern_2.png",
+        ]);
+        let pattern_png = image::open("res/outputs/test_pink_300_pattern_1.png");
+        assert!(pattern_png.is_ok());
+        assert_eq!(pattern_png.unwrap().width(), (210.0_f32 / 25.4 * 100.0).round() as u32);
+    }
+
     #[test]
     fn test_process_image_with_path_a3_max_32_colors() {
         let max_colors_count = 32;
@@ -231,7 +793,398 @@ mod test_generator {
 
         assert!(processing_result.len() <= max_colors_count);
     }
+
+    #[test]
+    fn test_process_image_with_config_a3_max_32_colors() {
+        let max_colors_count = 32;
+        let processing_result = full_generate_config_helper(
+            PaperSheet::standard_a3(),
+            PALETTE_PATH,
+            PaletteSelection::ClosestSubset,
+            "test_yellow_600.jpg",
+            max_colors_count,
+            CanvasSizing::default()
+        );
+    
+        assert!(processing_result.is_ok());
+        assert_summary(&processing_result.unwrap(), max_colors_count, &[
+            "res/outputs/test_yellow_600_config.pdf",
+            "res/outputs/test_yellow_600_config_preview.png",
+            "res/outputs/test_yellow_600_config_dmc_palette.json",
+        ]);
+    }
+
+    #[test]
+    fn test_process_image_with_config_a3_max_100_colors() {
+        let max_colors_count = 100;
+        let provided_dmc_palette = PaletteDmc::load_dmc_palette_from("res/palette_test_125_colors.json").unwrap();
+
+        // Swatch of every palette color, each spanning a few drills
+        let image_path = std::env::temp_dir().join("diamonds_imager_generator_swatch_125.png");
+        RgbImage::from_fn(550, 600, |x, y| {
+            let swatch_idx = (y / 50 * 11 + x / 50) as usize;
+            provided_dmc_palette.get(swatch_idx)
+                .map(|dmc| Rgb(dmc.color.0))
+                .unwrap_or(Rgb([255, 255, 255]))
+        }).save(&image_path).unwrap();
+
+        let config = GenerationConfig::builder(image_path)
+            .paper_sheet(PaperSheet::standard_a3())
+            .palette_path("res/palette_test_125_colors.json")
+            .max_colors(max_colors_count)
+            .color_distance_metric(ColorDistanceMetric::DeltaE2000)
+            .outputs(vec![PatternOutput::pdf("res/outputs/swatch_125.pdf")])
+            .build();
+
+        let summary = process_and_assert(&config, &["res/outputs/swatch_125.pdf"]);
+        assert_eq!(summary.dmc_palette.len(), max_colors_count);
+    }
     
+    #[test]
+    fn test_process_image_with_config_a4_tiled_canvas() {
+        let max_colors_count = 16;
+        let processing_result = full_generate_config_helper(
+            PaperSheet::standard_a4(),
+            PALETTE_PATH,
+            PaletteSelection::ClosestSubset,
+            "test_grass_300.png",
+            max_colors_count,
+            CanvasSizing {
+                policy: SizingPolicy::CanvasDrills(Size2U { w: 160, h: 120 }),
+                overlap_cells: 2,
+                ..Default::default()
+            }
+        );
+    
+        assert!(processing_result.is_ok());
+        assert_summary(&processing_result.unwrap(), max_colors_count, &[
+            "res/outputs/test_grass_300_config.pdf",
+            "res/outputs/test_grass_300_config_preview.png",
+        ]);
+    }
+
+    #[test]
+    fn test_process_image_with_config_drill_budget() {
+        let preview_path = "res/outputs/test_pink_300_budget_preview.png";
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .max_colors(8)
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::DrillBudget(2000),
+                aspect_fit: AspectFit::Fit,
+                ..Default::default()
+            })
+            .preview(preview_path, PreviewOptions {
+                pixels_per_drill: 1,
+                ..Default::default()
+            })
+            .outputs(vec![PatternOutput::pdf("res/outputs/test_pink_300_budget.pdf")])
+            .build();
+
+        process_and_assert(&config, &["res/outputs/test_pink_300_budget.pdf", preview_path]);
+        let preview = image::open(preview_path).unwrap();
+        assert!(preview.width() * preview.height() <= 2000);
+        assert!(preview.width() * preview.height() > 1800);
+    }
+
+    #[test]
+    fn test_process_image_with_config_drill_budget_cropped() {
+        let preview_path = "res/outputs/test_pink_300_budget_cropped_preview.png";
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .max_colors(8)
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::DrillBudget(2000),
+                aspect_fit: AspectFit::Fill,
+                crop_region: Some(Rect2U {
+                    pos: Pos2U { x: 50, y: 100 },
+                    size: Size2U { w: 200, h: 100 }
+                }),
+                ..Default::default()
+            })
+            .preview(preview_path, PreviewOptions {
+                pixels_per_drill: 1,
+                ..Default::default()
+            })
+            .outputs(vec![PatternOutput::pdf("res/outputs/test_pink_300_budget_cropped.pdf")])
+            .build();
+
+        process_and_assert(&config, &["res/outputs/test_pink_300_budget_cropped.pdf", preview_path]);
+        // Canvas follows 2:1 crop, not nearly square source image
+        let preview = image::open(preview_path).unwrap();
+        assert!(preview.width() >= 2 * preview.height() - 2, "{}x{}", preview.width(), preview.height());
+        assert!(preview.width() * preview.height() <= 2000);
+    }
+
+    #[test]
+    fn test_process_image_with_config_letterbox_border() {
+        let preview_path = "res/outputs/test_pink_300_letterbox_preview.png";
+        let config = GenerationConfig::builder("res/test_pink_300.jpg")
+            .max_colors(8)
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::CanvasDrills(Size2U { w: 60, h: 30 }),
+                aspect_fit: AspectFit::Letterbox,
+                border_dmc_code: Some("310".to_string()),
+                ..Default::default()
+            })
+            .preview(preview_path, PreviewOptions {
+                pixels_per_drill: 1,
+                ..Default::default()
+            })
+            .outputs(vec![PatternOutput::pdf("res/outputs/test_pink_300_letterbox.pdf")])
+            .build();
+
+        let summary = process_and_assert(&config, &["res/outputs/test_pink_300_letterbox.pdf", preview_path]);
+        let border_dmc = summary.dmc_palette.find_code_dmc("DMC 310").unwrap();
+
+        let preview = image::open(preview_path).unwrap().to_rgb8();
+        assert_eq!(preview.dimensions(), (60, 30));
+        assert_eq!(preview.get_pixel(0, 15).0, border_dmc.color.0);
+        assert_eq!(preview.get_pixel(59, 15).0, border_dmc.color.0);
+    }
+
+    #[test]
+    fn test_process_image_with_config_unknown_border_dmc() {
+        let processing_result = full_generate_config_helper(
+            PaperSheet::standard_a4(),
+            PALETTE_PATH,
+            PaletteSelection::ClosestSubset,
+            "test_pink_300.jpg",
+            8,
+            CanvasSizing {
+                aspect_fit: AspectFit::Letterbox,
+                border_dmc_code: Some("no such code".to_string()),
+                ..Default::default()
+            }
+        );
+        assert!(matches!(processing_result, Err(ProcessError::LoadDmcPaletteError(_))));
+    }
+
+    /// Opaque red disc on transparent black background
+    fn process_masked_disc(
+        masked_cells: MaskedCells,
+        confetti_reduction: Option<ConfettiReduction>,
+        name: &str
+    ) -> (ProcessSummary, RgbImage) {
+        let image_path = std::env::temp_dir().join(format!("diamonds_imager_generator_{name}.png"));
+        RgbaImage::from_fn(80, 80, |x, y| {
+            if (x as f32 - 39.5).hypot(y as f32 - 39.5) < 30.0 { Rgba([210, 40, 50, 255]) } else { Rgba([0, 0, 0, 0]) }
+        }).save(&image_path).unwrap();
+
+        let preview_path = format!("res/outputs/{name}_preview.png");
+        let config = GenerationConfig::builder(image_path)
+            .max_colors(4)
+            .diamond_shape(DiamondShape::common_square())
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::CanvasDrills(Size2U { w: 20, h: 20 }),
+                ..Default::default()
+            })
+            .mask(MaskOptions {
+                source: MaskSource::Alpha,
+                masked_cells
+            })
+            .confetti_reduction(confetti_reduction)
+            .preview(&preview_path, PreviewOptions {
+                pixels_per_drill: 1,
+                ..Default::default()
+            })
+            .outputs(vec![PatternOutput::pdf(&format!("res/outputs/{name}.pdf"))])
+            .build();
+        let processing_result = process_image_with_config(&config);
+
+        (processing_result.unwrap(), image::open(preview_path).unwrap().to_rgb8())
+    }
+
+    #[test]
+    fn test_process_image_with_config_alpha_mask_empty_cells() {
+        let (summary, preview) = process_masked_disc(MaskedCells::Empty, None, "alpha_mask_empty");
+
+        // Disc covers about 44% of 400 cells
+        assert!(summary.empty_cells_count > 200 && summary.empty_cells_count < 250);
+        assert_eq!(*preview.get_pixel(0, 0), Rgb(PreviewOptions::default().background_color));
+        // Transparent black does not darken disc edge nor end up in palette
+        assert!(summary.dmc_palette.iter().all(|dmc| dmc.color.0[0] > 120));
+    }
+
+    #[test]
+    fn test_process_image_with_config_fully_masked_image() {
+        let image_path = std::env::temp_dir().join("diamonds_imager_generator_fully_masked.png");
+        RgbaImage::from_pixel(40, 40, Rgba([0, 0, 0, 0])).save(&image_path).unwrap();
+
+        let config = GenerationConfig::builder(image_path)
+            .mask(MaskOptions {
+                source: MaskSource::Alpha,
+                masked_cells: MaskedCells::Empty
+            })
+            .outputs(vec![PatternOutput::pdf("res/outputs/fully_masked.pdf")])
+            .build();
+        let processing_result = process_image_with_config(&config);
+        assert!(matches!(processing_result, Err(ProcessError::NoCoveredCells)));
+    }
+
+    #[test]
+    fn test_process_image_with_config_alpha_mask_background_dmc() {
+        let (summary, preview) = process_masked_disc(MaskedCells::Background("310".to_string()), None, "alpha_mask_background");
+
+        assert_eq!(summary.empty_cells_count, 0);
+        let background_dmc = summary.dmc_palette.find_code_dmc("310").unwrap();
+        assert_eq!(preview.get_pixel(0, 0).0, background_dmc.color.0);
+        assert_ne!(preview.get_pixel(10, 10).0, background_dmc.color.0);
+    }
+
+    #[test]
+    fn test_process_image_with_config_alpha_mask_confetti_reduction() {
+        let confetti_reduction = Some(ConfettiReduction {
+            strength: 3,
+            max_color_distance: Some(f32::MAX)
+        });
+
+        let (plain_summary, _) = process_masked_disc(MaskedCells::Empty, None, "alpha_mask_empty_no_confetti");
+        let (summary, preview) = process_masked_disc(MaskedCells::Empty, confetti_reduction, "alpha_mask_empty_confetti");
+        // Empty cells are neither merged into disc nor used to recolor its edge
+        assert_eq!(summary.empty_cells_count, plain_summary.empty_cells_count);
+        assert_eq!(*preview.get_pixel(0, 0), Rgb(PreviewOptions::default().background_color));
+        assert!(summary.dmc_palette.iter().all(|dmc| dmc.color.0[0] > 120));
+
+        let (summary, preview) = process_masked_disc(
+            MaskedCells::Background("310".to_string()), 
+            confetti_reduction, 
+            "alpha_mask_background_confetti"
+        );
+        let background_dmc = summary.dmc_palette.find_code_dmc("310").unwrap();
+        // Background cells keep their drills, disc cells do not take background color
+        for (x, y) in [(0, 0), (19, 0), (0, 19), (19, 19), (10, 0), (0, 10)] {
+            assert_eq!(preview.get_pixel(x, y).0, background_dmc.color.0, "{x}, {y}");
+        }
+        for (x, y) in [(10, 10), (4, 10), (10, 4), (15, 10), (10, 15)] {
+            assert_ne!(preview.get_pixel(x, y).0, background_dmc.color.0, "{x}, {y}");
+        }
+    }
+
+    #[test]
+    fn test_image_adjustments_skip_letterbox_border() {
+        let border_color = Rgb([0, 0, 0]);
+        let fitted_image = FittedImage {
+            img: RgbImage::from_fn(6, 4, |x, _| if x == 0 || x == 5 { border_color } else { Rgb([200, 40, 40]) }),
+            mask: None,
+            content_cells: Rect2U {
+                pos: Pos2U { x: 1, y: 0 },
+                size: Size2U { w: 4, h: 4 }
+            }
+        };
+        let image_adjustments = ImageAdjustments {
+            steps: vec![
+                ImageAdjustment::Saturation { amount: 0.0 },
+                ImageAdjustment::Exposure { stops: 1.0 },
+            ]
+        };
+
+        let adjusted_image = apply_image_adjustments(fitted_image, &image_adjustments);
+        assert_eq!(*adjusted_image.img.get_pixel(0, 2), border_color);
+        assert_eq!(*adjusted_image.img.get_pixel(5, 2), border_color);
+        let [r, g, b] = adjusted_image.img.get_pixel(2, 2).0;
+        assert!(r == g && g == b && r > 80);
+    }
+
+    #[test]
+    fn test_process_image_with_config_fixed_palette() {
+        // Pink palette and green missing in the image
+        let palette_path = std::env::temp_dir().join("diamonds_imager_generator_pink_and_green.json");
+        let mut palette_colors: Vec<[u8; 3]> = serde_json::from_str(&std::fs::read_to_string("res/pink_8_colors.json").unwrap()).unwrap();
+        palette_colors.push([0, 200, 0]);
+        std::fs::write(&palette_path, serde_json::to_string(&palette_colors).unwrap()).unwrap();
+        let provided_dmc_palette = PaletteDmc::load_dmc_palette_from(&palette_path).unwrap();
+
+        let processing_result = full_generate_config_helper(
+            PaperSheet::standard_a4(),
+            &palette_path.to_string_lossy(),
+            PaletteSelection::Fixed,
+            "pink_8_colors_h_70.png",
+            4,
+            CanvasSizing::default()
+        );
+    
+        assert!(processing_result.is_ok());
+        let summary = processing_result.unwrap();
+        // Fixed palette is used whole, max colors count does not apply
+        assert_summary(&summary, provided_dmc_palette.len(), &["res/outputs/pink_8_colors_h_70_config.pdf"]);
+        assert_eq!(summary.dmc_palette, provided_dmc_palette);
+
+        // Unused color is saved without symbol
+        let saved_palette: PaletteDmcData = serde_json::from_str(
+            &std::fs::read_to_string("res/outputs/pink_8_colors_h_70_config_dmc_palette.json").unwrap()
+        ).unwrap();
+        let green = saved_palette.0.iter().find(|dmc_data| dmc_data.color == "#00C800").unwrap();
+        assert_eq!(green.count, Some(0));
+        assert_eq!(green.symbol, None);
+    }
+
+    #[test]
+    fn test_process_image_with_config_fixed_palette_letterbox_border() {
+        let provided_dmc_palette = PaletteDmc::load_dmc_palette_from("res/pink_8_colors.json").unwrap();
+        let border_dmc = provided_dmc_palette[0].clone();
+
+        let preview_path = "res/outputs/pink_8_colors_letterbox_preview.png";
+        let config = GenerationConfig::builder("res/pink_8_colors_h_70.png")
+            .palette_path("res/pink_8_colors.json")
+            .palette_selection(PaletteSelection::Fixed)
+            .canvas_sizing(CanvasSizing {
+                policy: SizingPolicy::CanvasDrills(Size2U { w: 60, h: 30 }),
+                aspect_fit: AspectFit::Letterbox,
+                border_dmc_code: Some(border_dmc.code.clone()),
+                ..Default::default()
+            })
+            .preview(preview_path, PreviewOptions {
+                pixels_per_drill: 1,
+                ..Default::default()
+            })
+            .dmc_palette_path("res/outputs/pink_8_colors_letterbox_dmc_palette.json")
+            .outputs(vec![PatternOutput::pdf("res/outputs/pink_8_colors_letterbox.pdf")])
+            .build();
+        remove_stale_outputs(&["res/outputs/pink_8_colors_letterbox.pdf", preview_path]);
+        let summary = process_image_with_config(&config).unwrap();
+        assert_summary(&summary, provided_dmc_palette.len(), &["res/outputs/pink_8_colors_letterbox.pdf", preview_path]);
+
+        // Border color is counted in legend like any other
+        assert!(summary.dmc_palette.find_code_dmc(&border_dmc.code).is_some());
+        let preview = image::open(preview_path).unwrap().to_rgb8();
+        assert_eq!(preview.get_pixel(0, 15).0, border_dmc.color.0);
+
+        // Border outside of provided palette is not looked up elsewhere
+        let config = GenerationConfig {
+            canvas_sizing: CanvasSizing {
+                border_dmc_code: Some("310".to_string()),
+                ..config.canvas_sizing.clone()
+            },
+            ..config
+        };
+        assert!(matches!(process_image_with_config(&config), Err(ProcessError::LoadDmcPaletteError(DmcError::CodeNotFound(_)))));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_process_image_with_path_keeps_original_signature() {
+        let provided_dmc_palette = PaletteDmc::load_dmc_palette_from("res/pink_8_colors.json").unwrap();
+        let output_path = "res/outputs/pink_8_colors_with_path.pdf";
+        let dmc_palette_path = "res/outputs/pink_8_colors_with_path_dmc_palette.json";
+        remove_stale_outputs(&[output_path, dmc_palette_path]);
+
+        let dmc_palette = super::process_image_with_path(
+            PaperSheet::standard_a4(),
+            provided_dmc_palette.clone(),
+            4,
+            DiamondShape::common_round(),
+            "res/pink_8_colors_h_70.png",
+            None,
+            Some(dmc_palette_path),
+            output_path
+        ).unwrap();
+
+        assert!(!dmc_palette.is_empty());
+        assert!(dmc_palette.len() <= 4);
+        assert!(dmc_palette.iter().all(|dmc| provided_dmc_palette.find_code_dmc(&dmc.code).is_some()));
+        assert!(Path::new(output_path).exists());
+        assert!(Path::new(dmc_palette_path).exists());
+    }
+
     #[test]
     fn test_find_subset_palette() {
         let provided_dmc_palette = PaletteDmc::load_dmc_palette().unwrap();
@@ -1,4 +1,21 @@
 pub mod types;
+pub mod paper;
 pub mod generator;
 pub mod dmc;
-pub mod render;
\ No newline at end of file
+pub mod render;
+pub mod tiling;
+pub mod sizing;
+pub mod adjustments;
+pub mod mask;
+pub mod dithering;
+pub mod confetti;
+pub mod colors;
+pub mod symbols;
+pub mod style;
+pub mod config;
+pub mod config_file;
+pub mod preview;
+pub mod backend;
+pub mod pdf_backend;
+pub mod svg_backend;
+pub mod png_backend;
\ No newline at end of file
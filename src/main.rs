@@ -1,12 +1,721 @@
-fn main()  {
-
-    // generate_project_pdf(
-    //     "res/pink_8_colors_h_70.png", 
-    //     "res/pink_8_colors.json", 
-    //     PaperSize::VerticalA4, 
-    //     2.0, 
-    //     0.5, 
-    //     "example.pdf"
-    // );
-
-}
\ No newline at end of file
+use std::{
+    path::PathBuf,
+    process::ExitCode
+};
+
+use clap::{
+    Args,
+    Parser,
+    Subcommand,
+    ValueEnum
+};
+use diamonds_imager_generator::{
+    adjustments::ImageAdjustments,
+    colors::ColorDistanceMetric,
+    config::GenerationConfig,
+    confetti::ConfettiReduction,
+    dithering::{
+        DitheringAlgorithm,
+        DitheringOptions
+    },
+    dmc::{
+        parse_hex_color,
+        DmcError,
+        DrillBagsConfig,
+        PaletteDmc
+    },
+    generator::{
+        process_image_with_config,
+        PaletteSelection,
+        ProcessSummary,
+        ProcessError
+    },
+    mask::{
+        MaskOptions,
+        MaskSource,
+        MaskedCells
+    },
+    paper::{
+        get_paper_presets,
+        parse_paper_sheet
+    },
+    preview::PreviewOptions,
+    render::{
+        OutputFormat,
+        PatternOutput,
+        PatternPages
+    },
+    sizing::{
+        AspectFit,
+        CanvasSizing,
+        ResamplingFilter,
+        SizingPolicy
+    },
+    style::RenderStyle,
+    types::{
+        DiamondShape,
+        Margins2D,
+        PaperSheet,
+        Pos2U,
+        Rect2U,
+        Size2D,
+        Size2U
+    }
+};
+use millimeter::mm;
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  2   invalid command line arguments
+  3   DMC palette can't be loaded, or DMC code not found
+  4   palette subset can't be picked
+  5   file can't be read or written
+  6   fixed palette has more colors than available symbols
+  7   reserved (formerly colors count mismatch)
+  8   style file can't be loaded
+  9   adjustments file can't be loaded
+  10  project file can't be loaded or saved
+  11  image can't be opened or saved
+  12  mask leaves no cell for drills";
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Diamond painting pattern generator", after_help = EXIT_CODES_HELP)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generate PDF pattern from an image
+    Generate(Box<GenerateArgs>),
+
+    /// Generate pattern from JSON or TOML project file, e.g. saved with `generate --save-config`
+    Run(RunArgs),
+
+    /// Find DMC threads matching a color, or substitutes for a DMC code
+    Match(MatchArgs),
+
+    /// List paper presets
+    Papers,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DiamondShapeArg {
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DitheringArg {
+    /// Nearest color, no dithering
+    None,
+    Bayer,
+    FloydSteinberg,
+    Atkinson,
+    JarvisJudiceNinke,
+    Stucki,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ColorMetricArg {
+    Rgb,
+    De76,
+    De94,
+    De2000,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AspectFitArg {
+    /// Fit for fit to paper, fill for exact canvas
+    Auto,
+    /// Whole image, canvas gets smaller along one axis
+    Fit,
+    /// Cut off image edges away from focal point
+    Fill,
+    /// Whole image with border around
+    Letterbox,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ResamplingFilterArg {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+    /// Mean of image pixels under each cell, best for large reductions
+    AreaAverage,
+}
+
+impl From<ResamplingFilterArg> for ResamplingFilter {
+    fn from(value: ResamplingFilterArg) -> Self {
+        match value {
+            ResamplingFilterArg::Nearest => ResamplingFilter::Nearest,
+            ResamplingFilterArg::Triangle => ResamplingFilter::Triangle,
+            ResamplingFilterArg::CatmullRom => ResamplingFilter::CatmullRom,
+            ResamplingFilterArg::Lanczos3 => ResamplingFilter::Lanczos3,
+            ResamplingFilterArg::AreaAverage => ResamplingFilter::AreaAverage,
+        }
+    }
+}
+
+impl From<AspectFitArg> for AspectFit {
+    fn from(value: AspectFitArg) -> Self {
+        match value {
+            AspectFitArg::Auto => AspectFit::Auto,
+            AspectFitArg::Fit => AspectFit::Fit,
+            AspectFitArg::Fill => AspectFit::Fill,
+            AspectFitArg::Letterbox => AspectFit::Letterbox,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PatternPagesArg {
+    Color,
+    /// Black symbols on white, saves ink
+    Symbols,
+    ColorAndSymbols,
+}
+
+impl From<PatternPagesArg> for PatternPages {
+    fn from(value: PatternPagesArg) -> Self {
+        match value {
+            PatternPagesArg::Color => PatternPages::Color,
+            PatternPagesArg::Symbols => PatternPages::Symbols,
+            PatternPagesArg::ColorAndSymbols => PatternPages::ColorAndSymbols,
+        }
+    }
+}
+
+impl From<ColorMetricArg> for ColorDistanceMetric {
+    fn from(value: ColorMetricArg) -> Self {
+        match value {
+            ColorMetricArg::Rgb => ColorDistanceMetric::Rgb,
+            ColorMetricArg::De76 => ColorDistanceMetric::DeltaE76,
+            ColorMetricArg::De94 => ColorDistanceMetric::DeltaE94,
+            ColorMetricArg::De2000 => ColorDistanceMetric::DeltaE2000,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    /// Input image
+    image: PathBuf,
+
+    /// Paper preset name like A2 or Letter, or size like 420x594mm, 61x90cm, 11x17in
+    #[arg(long, default_value = "A4", value_parser = parse_paper_arg)]
+    paper: PaperSheet,
+
+    /// Print margins in mm on every side, defaults to preset's margins
+    #[arg(long, conflicts_with = "paper_margins")]
+    paper_margin: Option<f32>,
+
+    /// Print margins in mm as top,right,bottom,left
+    #[arg(long, value_parser = parse_margins)]
+    paper_margins: Option<Margins2D>,
+
+    /// Diamond shape
+    #[arg(long, value_enum, default_value_t = DiamondShapeArg::Round)]
+    shape: DiamondShapeArg,
+
+    /// Diamond size in mm, defaults to common size of the shape
+    #[arg(long)]
+    diamond_size: Option<f32>,
+
+    /// Max colors count in the pattern
+    #[arg(long, default_value_t = 12)]
+    max_colors: usize,
+
+    /// DMC palette to pick colors from, DMC records or RGB triplets
+    #[arg(long, default_value = "res/palette_DMC.json")]
+    palette: PathBuf,
+
+    /// Use all palette colors as is, without picking closest subset
+    #[arg(long)]
+    fixed_palette: bool,
+
+    /// Color distance used for palette subset, dithering and confetti merging
+    #[arg(long, value_enum, default_value_t = ColorMetricArg::Rgb)]
+    color_metric: ColorMetricArg,
+
+    /// Dithering algorithm
+    #[arg(long, value_enum, default_value_t = DitheringArg::FloydSteinberg)]
+    dithering: DitheringArg,
+
+    /// Alternate error diffusion direction in every row
+    #[arg(long)]
+    serpentine: bool,
+
+    /// Merge same color clusters up to this size into neighbouring color
+    #[arg(long)]
+    confetti_strength: Option<usize>,
+
+    /// Max distance to neighbouring color allowed for confetti merging, defaults to metric dependent value
+    #[arg(long)]
+    confetti_max_distance: Option<f32>,
+
+    /// Exact canvas size in diamonds, e.g. 160x120. Pattern is split across many pages if needed
+    #[arg(long, value_parser = parse_size_pair::<u32>, conflicts_with_all = ["canvas_mm", "drill_budget"])]
+    canvas_diamonds: Option<(u32, u32)>,
+
+    /// Exact canvas size in mm, e.g. 400x500. Pattern is split across many pages if needed
+    #[arg(long, value_parser = parse_size_pair::<f32>, conflicts_with = "drill_budget")]
+    canvas_mm: Option<(f32, f32)>,
+
+    /// Largest canvas of image aspect ratio with at most that many drills
+    #[arg(long)]
+    drill_budget: Option<u32>,
+
+    /// How image is matched to canvas or printable area aspect ratio
+    #[arg(long, value_enum, default_value_t = AspectFitArg::Auto)]
+    aspect_fit: AspectFitArg,
+
+    /// Filter used to resize image to canvas cells
+    #[arg(long, value_enum, default_value_t = ResamplingFilterArg::AreaAverage)]
+    resampling_filter: ResamplingFilterArg,
+
+    /// Part of image used for pattern in pixels, as x,y,width,height
+    #[arg(long, value_parser = parse_crop_region)]
+    crop: Option<Rect2U>,
+
+    /// Point kept in view when filling, relative to image as x,y, e.g. 0.5,0.3
+    #[arg(long, value_parser = parse_focal_point)]
+    focal_point: Option<(f32, f32)>,
+
+    /// Letterbox border DMC code like 310 from provided palette, white border if not set. Added to picked colors if missing
+    #[arg(long)]
+    border_dmc: Option<String>,
+
+    /// Mask of cells to cover: "alpha" for image transparency or grayscale mask image path, black is masked out.
+    /// Any value other than "alpha" is treated as path
+    #[arg(long, value_parser = parse_mask_source)]
+    mask: Option<MaskSource>,
+
+    /// Masked out cells DMC code like 310 from provided palette, left empty if not set. Added to picked colors if missing
+    #[arg(long, requires = "mask")]
+    mask_background: Option<String>,
+
+    /// Cells repeated on neighbouring pages of tiled canvas
+    #[arg(long, default_value_t = 2)]
+    overlap: u32,
+
+    /// Drills in one bag, used to compute shopping list
+    #[arg(long, default_value_t = 200)]
+    bag_size: usize,
+
+    /// Spare drills percentage added to shopping list
+    #[arg(long, default_value_t = 10.0)]
+    spare_percentage: f32,
+
+    /// Pattern pages put in PDF
+    #[arg(long, value_enum, default_value_t = PatternPagesArg::Color)]
+    pattern_pages: PatternPagesArg,
+
+    /// Draw cell grid with thicker line every 10 cells and rulers around it
+    #[arg(long)]
+    grid: bool,
+
+    /// PDF style JSON or TOML file, e.g. fonts, lines, header and footer text
+    #[arg(long)]
+    style: Option<PathBuf>,
+
+    /// Pre-processing steps JSON or TOML file, e.g. auto levels, contrast and sharpening in given order
+    #[arg(long)]
+    adjustments: Option<PathBuf>,
+
+    /// Output PDF path
+    #[arg(short, long, default_value = "pattern.pdf")]
+    output: String,
+
+    /// Also write pattern as SVG, one numbered file per page
+    #[arg(long)]
+    svg: Option<String>,
+
+    /// Also write pattern as PNG, one numbered file per page
+    #[arg(long)]
+    png: Option<String>,
+
+    /// PNG output resolution
+    #[arg(long, default_value_t = 300.0)]
+    png_dpi: f32,
+
+    /// Output finished canvas preview PNG path
+    #[arg(long)]
+    preview: Option<PathBuf>,
+
+    /// Preview drill size in pixels, 1 saves one pixel per drill
+    #[arg(long, default_value_t = 12)]
+    preview_drill_px: u32,
+
+    /// Preview canvas background hex color like #ECE8E0
+    #[arg(long, value_parser = parse_background_color)]
+    preview_background: Option<[u8; 3]>,
+
+    /// Output used DMC palette JSON path
+    #[arg(long)]
+    palette_output: Option<PathBuf>,
+
+    /// Write project file with all settings, including style and adjustments, JSON or TOML by extension
+    #[arg(long)]
+    save_config: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    /// Project file, TOML if it has .toml extension, JSON otherwise
+    config: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct MatchArgs {
+    /// Hex color like #A0522D, or DMC code like 310 to find its substitutes
+    color: String,
+
+    /// Number of matches to show
+    #[arg(short = 'n', long, default_value_t = 5)]
+    count: usize,
+
+    /// Color distance metric
+    #[arg(long, value_enum, default_value_t = ColorMetricArg::De2000)]
+    color_metric: ColorMetricArg,
+
+    /// DMC palette to search
+    #[arg(long, default_value = "res/palette_DMC.json")]
+    palette: PathBuf,
+}
+
+impl GenerateArgs {
+    fn paper_sheet(&self) -> PaperSheet {
+        let mut paper_sheet = self.paper;
+        if let Some(margin) = self.paper_margin {
+            paper_sheet.print_margins = Margins2D::uniform(mm::new(margin));
+        }
+        if let Some(margins) = self.paper_margins {
+            paper_sheet.print_margins = margins;
+        }
+        paper_sheet
+    }
+
+    fn diamond_shape(&self) -> DiamondShape {
+        match (self.shape, self.diamond_size) {
+            (DiamondShapeArg::Round, None) => DiamondShape::common_round(),
+            (DiamondShapeArg::Square, None) => DiamondShape::common_square(),
+            (DiamondShapeArg::Round, Some(size)) => DiamondShape::Round { diameter: mm::new(size) },
+            (DiamondShapeArg::Square, Some(size)) => DiamondShape::Square { side: mm::new(size) },
+        }
+    }
+
+    fn dithering_options(&self) -> DitheringOptions {
+        let algorithm = match self.dithering {
+            DitheringArg::None => DitheringAlgorithm::Nearest,
+            DitheringArg::Bayer => DitheringAlgorithm::Bayer,
+            DitheringArg::FloydSteinberg => DitheringAlgorithm::FloydSteinberg,
+            DitheringArg::Atkinson => DitheringAlgorithm::Atkinson,
+            DitheringArg::JarvisJudiceNinke => DitheringAlgorithm::JarvisJudiceNinke,
+            DitheringArg::Stucki => DitheringAlgorithm::Stucki,
+        };
+        DitheringOptions {
+            algorithm,
+            serpentine: self.serpentine
+        }
+    }
+
+    fn canvas_sizing(&self) -> CanvasSizing {
+        let policy = match (self.canvas_diamonds, self.canvas_mm, self.drill_budget) {
+            (Some((w, h)), _, _) => SizingPolicy::CanvasDrills(Size2U { w, h }),
+            (None, Some((w, h)), _) => SizingPolicy::CanvasMillimeters(Size2D { w: mm::new(w), h: mm::new(h) }),
+            (None, None, Some(drills_count)) => SizingPolicy::DrillBudget(drills_count),
+            (None, None, None) => SizingPolicy::FitToPaper,
+        };
+        CanvasSizing {
+            policy,
+            aspect_fit: self.aspect_fit.into(),
+            resampling_filter: self.resampling_filter.into(),
+            crop_region: self.crop,
+            focal_point: self.focal_point,
+            border_dmc_code: self.border_dmc.clone(),
+            overlap_cells: self.overlap
+        }
+    }
+
+    fn mask_options(&self) -> MaskOptions {
+        MaskOptions {
+            source: self.mask.clone().unwrap_or_default(),
+            masked_cells: match &self.mask_background {
+                Some(code) => MaskedCells::Background(code.clone()),
+                None => MaskedCells::Empty,
+            }
+        }
+    }
+
+    fn preview_options(&self) -> PreviewOptions {
+        let default_options = PreviewOptions::default();
+        PreviewOptions {
+            pixels_per_drill: self.preview_drill_px,
+            background_color: self.preview_background.unwrap_or(default_options.background_color)
+        }
+    }
+
+    fn generation_config(&self) -> Result<GenerationConfig, ProcessError> {
+        let color_distance_metric = ColorDistanceMetric::from(self.color_metric);
+        let render_style = match &self.style {
+            Some(path) => RenderStyle::load_from(path)?,
+            None => RenderStyle::default(),
+        };
+        let image_adjustments = match &self.adjustments {
+            Some(path) => ImageAdjustments::load_from(path)?,
+            None => ImageAdjustments::default(),
+        };
+
+        let mut builder = GenerationConfig::builder(&self.image)
+            .paper_sheet(self.paper_sheet())
+            .diamond_shape(self.diamond_shape())
+            .palette_path(&self.palette)
+            .palette_selection(if self.fixed_palette { PaletteSelection::Fixed } else { PaletteSelection::ClosestSubset })
+            .max_colors(self.max_colors)
+            .color_distance_metric(color_distance_metric)
+            .dithering(self.dithering_options())
+            .confetti_reduction(self.confetti_strength.map(|strength| ConfettiReduction {
+                strength,
+                max_color_distance: self.confetti_max_distance
+            }))
+            .adjustments(image_adjustments)
+            .mask(self.mask_options())
+            .canvas_sizing(self.canvas_sizing())
+            .drill_bags(DrillBagsConfig {
+                bag_size: self.bag_size,
+                spare_percentage: self.spare_percentage
+            })
+            .pattern_pages(self.pattern_pages.into())
+            .style(render_style)
+            .grid(self.grid)
+            .outputs(self.pattern_outputs());
+        if let Some(path) = &self.preview {
+            builder = builder.preview(path, self.preview_options());
+        }
+        if let Some(path) = &self.palette_output {
+            builder = builder.dmc_palette_path(path);
+        }
+        Ok(builder.build())
+    }
+
+    fn pattern_outputs(&self) -> Vec<PatternOutput> {
+        let mut outputs = vec![PatternOutput::pdf(&self.output)];
+        if let Some(path) = &self.svg {
+            outputs.push(PatternOutput { format: OutputFormat::Svg, path: path.clone() });
+        }
+        if let Some(path) = &self.png {
+            outputs.push(PatternOutput { format: OutputFormat::Png { dpi: self.png_dpi }, path: path.clone() });
+        }
+        outputs
+    }
+}
+
+/// Parses "WxH" pair, drill counts are parsed as integers
+fn parse_size_pair<T>(value: &str) -> Result<(T, T), String>
+where
+    T: std::str::FromStr + PartialOrd + Default,
+    T::Err: std::fmt::Display
+{
+    let (w, h) = value.split_once(['x', 'X'])
+        .ok_or(format!("expected WxH, got '{value}'"))?;
+    let w: T = w.trim().parse().map_err(|err| format!("bad width '{w}': {err}"))?;
+    let h: T = h.trim().parse().map_err(|err| format!("bad height '{h}': {err}"))?;
+    if w <= T::default() || h <= T::default() {
+        return Err(format!("size must be positive, got '{value}'"));
+    }
+    Ok((w, h))
+}
+
+fn parse_numbers<T: std::str::FromStr>(value: &str) -> Result<Vec<T>, String>
+where
+    T::Err: std::fmt::Display
+{
+    value.split(',')
+        .map(|number| number.trim().parse::<T>().map_err(|err| format!("bad number '{number}': {err}")))
+        .collect()
+}
+
+/// "alpha" in any case, anything else is mask image path
+fn parse_mask_source(value: &str) -> Result<MaskSource, String> {
+    if value.eq_ignore_ascii_case("alpha") {
+        return Ok(MaskSource::Alpha);
+    }
+    let path = PathBuf::from(value);
+    if !path.is_file() {
+        return Err(format!("mask image '{value}' not found, values other than 'alpha' are treated as mask image path"));
+    }
+    Ok(MaskSource::Image(path))
+}
+
+/// Parses "x,y,width,height" in pixels
+fn parse_crop_region(value: &str) -> Result<Rect2U, String> {
+    match parse_numbers::<u32>(value)?[..] {
+        [x, y, w, h] if w > 0 && h > 0 => Ok(Rect2U {
+            pos: Pos2U { x, y },
+            size: Size2U { w, h }
+        }),
+        _ => Err(format!("expected x,y,width,height with positive size, got '{value}'")),
+    }
+}
+
+/// Parses "x,y" relative to image size
+fn parse_focal_point(value: &str) -> Result<(f32, f32), String> {
+    match parse_numbers::<f32>(value)?[..] {
+        [x, y] if (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y) => Ok((x, y)),
+        _ => Err(format!("expected x,y between 0 and 1, got '{value}'")),
+    }
+}
+
+fn parse_paper_arg(value: &str) -> Result<PaperSheet, String> {
+    parse_paper_sheet(value).map_err(|err| err.to_string())
+}
+
+/// Parses "top,right,bottom,left" margins in mm
+fn parse_margins(value: &str) -> Result<Margins2D, String> {
+    let margins = parse_numbers::<f32>(value)?;
+    match margins[..] {
+        [top, right, bottom, left] if margins.iter().all(|margin| *margin >= 0.0) => Ok(Margins2D {
+            top: mm::new(top),
+            right: mm::new(right),
+            bottom: mm::new(bottom),
+            left: mm::new(left)
+        }),
+        _ => Err(format!("expected 4 non-negative margins top,right,bottom,left, got '{value}'")),
+    }
+}
+
+fn parse_background_color(value: &str) -> Result<[u8; 3], String> {
+    parse_hex_color(value)
+        .map(|color| color.0)
+        .map_err(|err| err.to_string())
+}
+
+/// Listed in `EXIT_CODES_HELP`, 2 is left to clap usage errors
+fn process_error_exit_code(err: &ProcessError) -> u8 {
+    match err {
+        ProcessError::ImageError(_) => 11,
+        ProcessError::LoadDmcPaletteError(_) => 3,
+        ProcessError::PaletteError(_) => 4,
+        ProcessError::IoError(_) => 5,
+        ProcessError::PaletteTooLarge { .. } => 6,
+        ProcessError::RenderStyleError(_) => 8,
+        ProcessError::ImageAdjustmentsError(_) => 9,
+        ProcessError::GenerationConfigError(_) => 10,
+        ProcessError::NoCoveredCells => 12,
+    }
+}
+
+/// I/O failures share code with `process_error_exit_code`
+fn dmc_error_exit_code(err: &DmcError) -> u8 {
+    match err {
+        DmcError::IoError(_) => 5,
+        _ => 3,
+    }
+}
+
+fn print_process_summary(config: &GenerationConfig, process_summary: &ProcessSummary) {
+    let output_paths = config.outputs.iter()
+        .map(|output| format!("'{}'", output.path))
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("Generated {output_paths} using {} DMC colors", process_summary.dmc_palette.len());
+    if config.confetti_reduction.is_some() {
+        println!("Confetti reduction changed {} cells", process_summary.confetti_cells_changed);
+    }
+    if process_summary.empty_cells_count > 0 {
+        println!("Left {} cells empty", process_summary.empty_cells_count);
+    }
+}
+
+fn generate(args: GenerateArgs) -> Result<(), ProcessError> {
+    let config = args.generation_config()?;
+    if let Some(path) = &args.save_config {
+        config.save_to(path)?;
+    }
+
+    let process_summary = process_image_with_config(&config)?;
+    print_process_summary(&config, &process_summary);
+    Ok(())
+}
+
+fn run(args: RunArgs) -> Result<(), ProcessError> {
+    let config = GenerationConfig::load_from(&args.config)?;
+
+    let process_summary = process_image_with_config(&config)?;
+    print_process_summary(&config, &process_summary);
+    Ok(())
+}
+
+fn match_color(args: MatchArgs) -> Result<(), DmcError> {
+    let palette = PaletteDmc::load_dmc_palette_from(&args.palette)?;
+    let metric = ColorDistanceMetric::from(args.color_metric);
+
+    let hex_color = if args.color.starts_with('#') {
+        args.color.clone()
+    } else {
+        format!("#{}", args.color)
+    };
+
+    let matches = match parse_hex_color(&hex_color) {
+        Ok(color) => palette.find_k_nearest_dmc(color, metric, args.count),
+        Err(_) => {
+            // Substitutes for out of stock thread, skipping the thread itself
+            let dmc = palette.find_code_dmc(&args.color)
+                .ok_or(DmcError::CodeNotFound(args.color.clone()))?;
+            println!("{} {} #{:02X}{:02X}{:02X}", dmc.code, dmc.name, dmc.color[0], dmc.color[1], dmc.color[2]);
+
+            let mut matches = palette.find_k_nearest_dmc(dmc.color, metric, args.count + 1);
+            matches.retain(|dmc_match| dmc_match.dmc != dmc);
+            matches.truncate(args.count);
+            matches
+        },
+    };
+
+    for dmc_match in matches {
+        let color = dmc_match.dmc.color;
+        println!("{:<12} {:<28} #{:02X}{:02X}{:02X} {:>8.2}",
+            dmc_match.dmc.code,
+            dmc_match.dmc.name,
+            color[0],
+            color[1],
+            color[2],
+            dmc_match.distance
+        );
+    }
+
+    Ok(())
+}
+
+fn list_papers() {
+    for preset in get_paper_presets() {
+        println!("{:<8} {:>6.1} x {:>6.1} mm, margin {} mm", preset.name, preset.width_mm, preset.height_mm, preset.margin_mm);
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Generate(args) => generate(*args)
+            .map_err(|err| (err.to_string(), process_error_exit_code(&err))),
+        Command::Run(args) => run(args)
+            .map_err(|err| (err.to_string(), process_error_exit_code(&err))),
+        Command::Match(args) => match_color(args)
+            .map_err(|err| (err.to_string(), dmc_error_exit_code(&err))),
+        Command::Papers => {
+            list_papers();
+            Ok(())
+        },
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err((message, exit_code)) => {
+            eprintln!("Error: {message}");
+            ExitCode::from(exit_code)
+        }
+    }
+}
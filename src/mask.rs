@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+
+use image::{
+    DynamicImage,
+    GrayImage,
+    ImageError,
+    Luma,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    sizing::ResamplingFilter,
+    types::Size2U
+};
+
+/// Cell is covered when at least half of its pixels are kept by mask
+const COVERED_CELL_MIN_COVERAGE: u8 = 128;
+
+/// Where mask comes from, kept pixels are opaque or white
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskSource {
+    /// Every cell is covered
+    #[default]
+    None,
+    /// Alpha channel of source image
+    Alpha,
+    /// Grayscale image, stretched to source image size
+    Image(PathBuf),
+}
+
+/// What masked out cells become
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskedCells {
+    /// Left without drills on partial canvas
+    #[default]
+    Empty,
+    /// Covered with drills of DMC code like "310" from provided palette
+    Background(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskOptions {
+    pub source: MaskSource,
+    pub masked_cells: MaskedCells,
+}
+
+/// Cells left without drills, none by default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellsMask(Option<GrayImage>);
+
+/// Mask in source image pixels, 255 for kept pixel, mask image of other size resized by `resampling_filter`
+pub fn load_mask(
+    img: &DynamicImage,
+    mask_source: &MaskSource,
+    resampling_filter: ResamplingFilter
+) -> Result<Option<GrayImage>, ImageError> {
+    match mask_source {
+        MaskSource::None => Ok(None),
+        MaskSource::Alpha if !img.color().has_alpha() => Ok(None),
+        MaskSource::Alpha => {
+            let alpha = img.to_luma_alpha8();
+            Ok(Some(GrayImage::from_fn(img.width(), img.height(), |x, y| Luma([alpha.get_pixel(x, y).0[1]]))))
+        },
+        MaskSource::Image(path) => {
+            let mask = image::open(path)?.to_luma8();
+            if mask.dimensions() == (img.width(), img.height()) {
+                Ok(Some(mask))
+            } else {
+                Ok(Some(resampling_filter.resize(&mask, Size2U { w: img.width(), h: img.height() })))
+            }
+        },
+    }
+}
+
+impl CellsMask {
+    /// `coverage` is mask resized to canvas cells
+    pub fn from_coverage(coverage: &GrayImage) -> Self {
+        let has_empty_cells = coverage.pixels().any(|px| px.0[0] < COVERED_CELL_MIN_COVERAGE);
+        Self(has_empty_cells.then(|| coverage.clone()))
+    }
+
+    pub fn is_empty_cell(&self, x: u32, y: u32) -> bool {
+        self.0.as_ref()
+            .is_some_and(|coverage| coverage.get_pixel(x, y).0[0] < COVERED_CELL_MIN_COVERAGE)
+    }
+
+    pub fn has_empty_cells(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn get_empty_cells_count(&self) -> usize {
+        self.0.as_ref()
+            .map(|coverage| coverage.pixels().filter(|px| px.0[0] < COVERED_CELL_MIN_COVERAGE).count())
+            .unwrap_or(0)
+    }
+
+    /// Covered cells as single row image, e.g. for palette selection
+    pub fn get_covered_pixels(&self, img: &RgbImage) -> RgbImage {
+        let covered_pixels = img.enumerate_pixels()
+            .filter(|(x, y, _)| !self.is_empty_cell(*x, *y))
+            .flat_map(|(_, _, px)| px.0)
+            .collect::<Vec<_>>();
+        let covered_count = covered_pixels.len() as u32 / 3;
+        RgbImage::from_raw(covered_count, covered_count.min(1), covered_pixels)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test_mask {
+    use image::{
+        DynamicImage,
+        GrayImage,
+        Luma,
+        Rgb,
+        RgbImage,
+        Rgba,
+        RgbaImage
+    };
+
+    use crate::sizing::ResamplingFilter;
+
+    use super::{
+        load_mask,
+        CellsMask,
+        MaskSource
+    };
+
+    #[test]
+    fn test_alpha_mask() {
+        let img = RgbaImage::from_fn(4, 2, |x, _| if x < 2 { Rgba([10, 20, 30, 0]) } else { Rgba([10, 20, 30, 255]) });
+        let mask = load_mask(&DynamicImage::ImageRgba8(img), &MaskSource::Alpha, ResamplingFilter::default()).unwrap().unwrap();
+        assert_eq!(mask.get_pixel(0, 0).0, [0]);
+        assert_eq!(mask.get_pixel(3, 1).0, [255]);
+
+        let opaque_img = DynamicImage::ImageRgb8(RgbImage::new(4, 2));
+        assert!(load_mask(&opaque_img, &MaskSource::Alpha, ResamplingFilter::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cells_mask() {
+        let coverage = GrayImage::from_fn(3, 2, |x, _| Luma([[0, 127, 200][x as usize]]));
+        let cells_mask = CellsMask::from_coverage(&coverage);
+        assert!(cells_mask.is_empty_cell(0, 0));
+        assert!(cells_mask.is_empty_cell(1, 1));
+        assert!(!cells_mask.is_empty_cell(2, 0));
+        assert_eq!(cells_mask.get_empty_cells_count(), 4);
+
+        let img = RgbImage::from_fn(3, 2, |x, y| Rgb([x as u8, y as u8, 0]));
+        let covered_pixels = cells_mask.get_covered_pixels(&img);
+        assert_eq!(covered_pixels.dimensions(), (2, 1));
+        assert_eq!(covered_pixels.get_pixel(1, 0).0, [2, 1, 0]);
+
+        assert_eq!(CellsMask::from_coverage(&GrayImage::from_pixel(3, 2, Luma([255]))), CellsMask::default());
+    }
+}
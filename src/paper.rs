@@ -0,0 +1,178 @@
+use millimeter::mm;
+
+use crate::types::{
+    Margins2D,
+    PaperSheet,
+    Size2D
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PaperError {
+    #[error("Unknown paper preset: {0}")]
+    UnknownPreset(String),
+
+    #[error("Failed to parse paper size: {0}, expected e.g. 420x594mm or 11x17in")]
+    SizeParseFailed(String),
+}
+
+/// Portrait dimensions with margin suitable for common printers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperPreset {
+    pub name: &'static str,
+    pub width_mm: f32,
+    pub height_mm: f32,
+    pub margin_mm: f32,
+}
+
+const PAPER_PRESETS: &[PaperPreset] = &[
+    PaperPreset { name: "A0", width_mm: 841.0, height_mm: 1189.0, margin_mm: 15.0 },
+    PaperPreset { name: "A1", width_mm: 594.0, height_mm: 841.0, margin_mm: 12.0 },
+    PaperPreset { name: "A2", width_mm: 420.0, height_mm: 594.0, margin_mm: 10.0 },
+    PaperPreset { name: "A3", width_mm: 297.0, height_mm: 420.0, margin_mm: 8.0 },
+    PaperPreset { name: "A4", width_mm: 210.0, height_mm: 297.0, margin_mm: 6.0 },
+    PaperPreset { name: "A5", width_mm: 148.0, height_mm: 210.0, margin_mm: 5.0 },
+    PaperPreset { name: "A6", width_mm: 105.0, height_mm: 148.0, margin_mm: 4.0 },
+    PaperPreset { name: "B3", width_mm: 353.0, height_mm: 500.0, margin_mm: 10.0 },
+    PaperPreset { name: "B4", width_mm: 250.0, height_mm: 353.0, margin_mm: 8.0 },
+    PaperPreset { name: "B5", width_mm: 176.0, height_mm: 250.0, margin_mm: 6.0 },
+    PaperPreset { name: "Letter", width_mm: 215.9, height_mm: 279.4, margin_mm: 6.35 },
+    PaperPreset { name: "Legal", width_mm: 215.9, height_mm: 355.6, margin_mm: 6.35 },
+    PaperPreset { name: "Tabloid", width_mm: 279.4, height_mm: 431.8, margin_mm: 8.0 },
+];
+
+/// Used for sizes parsed from string, e.g. roll widths
+const CUSTOM_PAPER_MARGIN_MM: f32 = 6.0;
+
+impl PaperPreset {
+    pub fn to_paper_sheet(&self) -> PaperSheet {
+        PaperSheet {
+            size: Size2D {
+                w: mm::new(self.width_mm),
+                h: mm::new(self.height_mm)
+            },
+            print_margins: Margins2D::uniform(mm::new(self.margin_mm))
+        }
+    }
+}
+
+pub fn get_paper_presets() -> &'static [PaperPreset] {
+    PAPER_PRESETS
+}
+
+/// Case insensitive, "a2" and "letter" work too
+pub fn find_paper_preset(name: &str) -> Option<&'static PaperPreset> {
+    PAPER_PRESETS.iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// "WxH" followed by unit: mm, cm or in. Size without unit is in mm
+fn parse_paper_size(value: &str) -> Option<Size2D> {
+    let value = value.trim().to_ascii_lowercase();
+    let (value, mm_per_unit) = [("mm", 1.0), ("cm", 10.0), ("in", 25.4)].iter()
+        .find_map(|(unit, mm_per_unit)| value.strip_suffix(unit).map(|value| (value.to_string(), *mm_per_unit)))
+        .unwrap_or((value.clone(), 1.0));
+
+    let (w, h) = value.split_once('x')?;
+    let w: f32 = w.trim().parse().ok()?;
+    let h: f32 = h.trim().parse().ok()?;
+    if !(w > 0.0 && h > 0.0) {
+        return None;
+    }
+
+    Some(Size2D {
+        w: mm::new(w * mm_per_unit),
+        h: mm::new(h * mm_per_unit)
+    })
+}
+
+/// Preset name like "A2" or size like "420x594mm", "42x59.4cm", "11x17in"
+pub fn parse_paper_sheet(value: &str) -> Result<PaperSheet, PaperError> {
+    if let Some(preset) = find_paper_preset(value) {
+        return Ok(preset.to_paper_sheet());
+    }
+
+    let starts_with_digit = value.trim().starts_with(|c: char| c.is_ascii_digit());
+    match parse_paper_size(value) {
+        Some(size) => Ok(PaperSheet {
+            size,
+            print_margins: Margins2D::uniform(mm::new(CUSTOM_PAPER_MARGIN_MM))
+        }),
+        None if starts_with_digit => Err(PaperError::SizeParseFailed(value.to_string())),
+        None => Err(PaperError::UnknownPreset(value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test_paper {
+    use millimeter::mm;
+
+    use crate::types::{
+        Margins2D,
+        PaperSheet
+    };
+
+    use super::{
+        find_paper_preset,
+        parse_paper_sheet,
+        PaperError
+    };
+
+    #[test]
+    fn test_presets_lookup() {
+        let a3 = find_paper_preset("a3").unwrap().to_paper_sheet();
+        let standard_a3 = PaperSheet::standard_a3();
+        assert_eq!(a3.size.w.raw_value(), standard_a3.size.w.raw_value());
+        assert_eq!(a3.size.h.raw_value(), standard_a3.size.h.raw_value());
+        assert_eq!(a3.print_margins.left.raw_value(), standard_a3.print_margins.left.raw_value());
+
+        assert_eq!(find_paper_preset("Tabloid").unwrap().height_mm, 431.8);
+        assert!(find_paper_preset("A7").is_none());
+    }
+
+    #[test]
+    fn test_parse_paper_sizes() {
+        let a2 = parse_paper_sheet("420x594mm").unwrap();
+        assert_eq!(a2.size.w.raw_value(), 420.0);
+        assert_eq!(a2.size.h.raw_value(), 594.0);
+
+        let tabloid = parse_paper_sheet("11x17in").unwrap();
+        assert!((tabloid.size.w.raw_value() - 279.4).abs() < 0.01);
+        assert!((tabloid.size.h.raw_value() - 431.8).abs() < 0.01);
+
+        let roll = parse_paper_sheet("61 x 90 cm").unwrap();
+        assert_eq!(roll.size.w.raw_value(), 610.0);
+
+        assert_eq!(parse_paper_sheet("legal").unwrap().size.h.raw_value(), 355.6);
+    }
+
+    #[test]
+    fn test_parse_paper_errors() {
+        assert!(matches!(parse_paper_sheet("A9"), Err(PaperError::UnknownPreset(_))));
+        assert!(matches!(parse_paper_sheet("420x"), Err(PaperError::SizeParseFailed(_))));
+        assert!(matches!(parse_paper_sheet("0x594mm"), Err(PaperError::SizeParseFailed(_))));
+    }
+
+    #[test]
+    fn test_per_side_margins() {
+        let mut paper_sheet = parse_paper_sheet("A4").unwrap();
+        paper_sheet.print_margins = Margins2D {
+            top: mm::new(20.0),
+            right: mm::new(5.0),
+            bottom: mm::new(10.0),
+            left: mm::new(15.0)
+        };
+
+        let printing_area_rect = paper_sheet.get_printing_area_rect();
+        assert_eq!(printing_area_rect.left().raw_value(), 15.0);
+        assert_eq!(printing_area_rect.bottom().raw_value(), 10.0);
+        assert_eq!(printing_area_rect.size.w.raw_value(), 190.0);
+        assert_eq!(printing_area_rect.size.h.raw_value(), 267.0);
+
+        // Binding edge stays with its side of the sheet
+        paper_sheet.change_orientation();
+        let printing_area_rect = paper_sheet.get_printing_area_rect();
+        assert_eq!(paper_sheet.print_margins.top.raw_value(), 5.0);
+        assert_eq!(printing_area_rect.size.w.raw_value(), 297.0 - 30.0);
+        assert_eq!(printing_area_rect.size.h.raw_value(), 210.0 - 20.0);
+    }
+}
@@ -0,0 +1,548 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{
+        self,
+        Write
+    },
+    path::Path
+};
+
+use ab_glyph::{
+    Font,
+    FontRef,
+    GlyphId
+};
+use image::Rgb;
+use pdf_writer::{
+    types::{
+        CidFontType,
+        FontFlags,
+        SystemInfo,
+        UnicodeCmap
+    },
+    writers::Resources,
+    Content,
+    Name,
+    Pdf,
+    Rect,
+    Ref,
+    Str
+};
+use subsetter::GlyphRemapper;
+
+use crate::backend::{
+    PatternCanvas,
+    PatternDocument,
+    PatternFont
+};
+
+/// Glyph widths in PDF are given per 1000 units of font size
+const PDF_GLYPH_UNITS: f32 = 1000.0;
+
+/// Bezier control points distance approximating quarter of a circle
+const CIRCLE_BEZIER_FACTOR: f32 = 0.551_915;
+
+const IDENTITY_SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+fn invalid_data<E: ToString>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err.to_string())
+}
+
+/// Font embedded as subset of glyphs drawn on pages.
+/// Glyphs are remapped to subset glyph ids, which are also CIDs of drawn text
+struct EmbeddedFont {
+    pattern_font: PatternFont,
+    font: FontRef<'static>,
+    glyph_remapper: GlyphRemapper,
+    /// Subset glyph id to drawn character, used for text extraction
+    chars: BTreeMap<u16, char>,
+}
+
+/// Fonts used on pages, written once all pages are rendered
+struct PdfFonts {
+    sans: EmbeddedFont,
+    sans_bold: EmbeddedFont,
+    mono: EmbeddedFont,
+    mono_bold: EmbeddedFont,
+    serif: EmbeddedFont,
+    serif_bold: EmbeddedFont,
+}
+
+impl EmbeddedFont {
+    fn load(pattern_font: PatternFont) -> io::Result<Self> {
+        Ok(Self {
+            pattern_font,
+            font: FontRef::try_from_slice(pattern_font.get_data()).map_err(invalid_data)?,
+            glyph_remapper: GlyphRemapper::new(),
+            chars: BTreeMap::new(),
+        })
+    }
+
+    fn has_glyph(&self, c: char) -> bool {
+        self.font.glyph_id(c).0 != 0
+    }
+
+    fn is_used(&self) -> bool {
+        !self.chars.is_empty()
+    }
+
+    fn get_resource_name(&self) -> String {
+        format!("F{}", self.pattern_font as usize)
+    }
+
+    fn get_advance(&self, glyph_id: GlyphId) -> f32 {
+        let units_per_em = self.font.units_per_em().unwrap_or(PDF_GLYPH_UNITS);
+        self.font.h_advance_unscaled(glyph_id) * PDF_GLYPH_UNITS / units_per_em
+    }
+
+    fn get_text_width(&self, size: f32, text: &str) -> f32 {
+        text.chars()
+            .map(|c| self.get_advance(self.font.glyph_id(c)))
+            .sum::<f32>() * size / PDF_GLYPH_UNITS
+    }
+
+    /// Bytes of drawn text, 2 byte CIDs
+    fn encode_text(&mut self, text: &str) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| {
+                let cid = self.glyph_remapper.remap(self.font.glyph_id(c).0);
+                self.chars.insert(cid, c);
+                cid.to_be_bytes()
+            })
+            .collect()
+    }
+
+    /// Type0 font with single CIDFontType2 descendant, returns Type0 font id
+    fn write(&self, pdf: &mut Pdf, next_id: &mut Ref) -> io::Result<Ref> {
+        let subset_data = subsetter::subset(self.pattern_font.get_data(), 0, &self.glyph_remapper)
+            .map_err(invalid_data)?;
+
+        // Subset tag is 6 uppercase letters, derived from used glyphs
+        let tag_seed = self.chars.values()
+            .fold(0u32, |seed, c| seed.wrapping_mul(31).wrapping_add(*c as u32));
+        let tag = (0..6)
+            .map(|idx| (b'A' + ((tag_seed >> (idx * 5)) % 26) as u8) as char)
+            .collect::<String>();
+        let font_name = format!("{tag}+{}", self.pattern_font.get_name());
+
+        let units_per_em = self.font.units_per_em().unwrap_or(PDF_GLYPH_UNITS);
+        let to_pdf_units = |value: f32| (value * PDF_GLYPH_UNITS / units_per_em).round();
+        let ascent = to_pdf_units(self.font.ascent_unscaled());
+        let descent = to_pdf_units(self.font.descent_unscaled());
+        let mut bbox = Rect::new(0.0, descent, 0.0, ascent);
+        // Subset glyph ids are consecutive, starting from .notdef
+        let mut widths = Vec::new();
+        for old_glyph_id in self.glyph_remapper.remapped_gids() {
+            let glyph_id = GlyphId(old_glyph_id);
+            if let Some(outline) = self.font.outline(glyph_id) {
+                bbox.x1 = bbox.x1.min(to_pdf_units(outline.bounds.min.x));
+                bbox.y1 = bbox.y1.min(to_pdf_units(outline.bounds.min.y));
+                bbox.x2 = bbox.x2.max(to_pdf_units(outline.bounds.max.x));
+                bbox.y2 = bbox.y2.max(to_pdf_units(outline.bounds.max.y));
+            }
+            widths.push(self.get_advance(glyph_id).round());
+        }
+
+        let mut to_unicode = UnicodeCmap::<u16>::new(Name(b"Custom"), IDENTITY_SYSTEM_INFO);
+        for (cid, c) in &self.chars {
+            to_unicode.pair(*cid, *c);
+        }
+        let to_unicode = to_unicode.finish();
+
+        let [type0_id, cid_font_id, descriptor_id, font_file_id, to_unicode_id] = [(); 5].map(|_| next_id.bump());
+        pdf.type0_font(type0_id)
+            .base_font(Name(font_name.as_bytes()))
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_font_id)
+            .to_unicode(to_unicode_id);
+        pdf.cid_font(cid_font_id)
+            .subtype(CidFontType::Type2)
+            .base_font(Name(font_name.as_bytes()))
+            .system_info(IDENTITY_SYSTEM_INFO)
+            .font_descriptor(descriptor_id)
+            .cid_to_gid_map_predefined(Name(b"Identity"))
+            .widths()
+            .consecutive(0, widths);
+        pdf.font_descriptor(descriptor_id)
+            .name(Name(font_name.as_bytes()))
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(bbox)
+            .italic_angle(0.0)
+            .ascent(ascent)
+            .descent(descent)
+            .cap_height(ascent)
+            .stem_v(120.0)
+            .font_file2(font_file_id);
+        pdf.stream(font_file_id, &subset_data)
+            .pair(Name(b"Length1"), subset_data.len() as i32);
+        pdf.cmap(to_unicode_id, &to_unicode);
+        Ok(type0_id)
+    }
+}
+
+impl PdfFonts {
+    fn load() -> io::Result<Self> {
+        Ok(Self {
+            sans: EmbeddedFont::load(PatternFont::Sans)?,
+            sans_bold: EmbeddedFont::load(PatternFont::SansBold)?,
+            mono: EmbeddedFont::load(PatternFont::Mono)?,
+            mono_bold: EmbeddedFont::load(PatternFont::MonoBold)?,
+            serif: EmbeddedFont::load(PatternFont::Serif)?,
+            serif_bold: EmbeddedFont::load(PatternFont::SerifBold)?,
+        })
+    }
+
+    fn get(&self, font: PatternFont) -> &EmbeddedFont {
+        match font {
+            PatternFont::Sans => &self.sans,
+            PatternFont::SansBold => &self.sans_bold,
+            PatternFont::Mono => &self.mono,
+            PatternFont::MonoBold => &self.mono_bold,
+            PatternFont::Serif => &self.serif,
+            PatternFont::SerifBold => &self.serif_bold,
+        }
+    }
+
+    fn get_mut(&mut self, font: PatternFont) -> &mut EmbeddedFont {
+        match font {
+            PatternFont::Sans => &mut self.sans,
+            PatternFont::SansBold => &mut self.sans_bold,
+            PatternFont::Mono => &mut self.mono,
+            PatternFont::MonoBold => &mut self.mono_bold,
+            PatternFont::Serif => &mut self.serif,
+            PatternFont::SerifBold => &mut self.serif_bold,
+        }
+    }
+
+    /// Consecutive characters drawn by the same font, glyphs missing in `font` fall back to sans as in PNG
+    fn get_font_runs(&self, font: PatternFont, text: &str) -> Vec<(PatternFont, String)> {
+        let mut runs: Vec<(PatternFont, String)> = Vec::new();
+        for c in text.chars() {
+            let glyph_font = if self.get(font).has_glyph(c) { font } else { PatternFont::Sans };
+            match runs.last_mut() {
+                Some((run_font, run)) if *run_font == glyph_font => run.push(c),
+                _ => runs.push((glyph_font, c.to_string())),
+            }
+        }
+        runs
+    }
+
+    fn get_text_width(&self, font: PatternFont, size: f32, text: &str) -> f32 {
+        self.get_font_runs(font, text).iter()
+            .map(|(run_font, run)| self.get(*run_font).get_text_width(size, run))
+            .sum()
+    }
+}
+
+/// Pages are serialized as they are rendered and the file is written on `finish`,
+/// because embedded font subsets are known only after the last page.
+/// All texts use embedded subsets of DejaVu fonts
+pub struct PdfDocument {
+    /// Opened on create, so bad path fails before rendering
+    output_file: File,
+    pdf: Pdf,
+    next_id: Ref,
+    catalog_id: Ref,
+    pages_id: Ref,
+    /// Shared by all pages, fonts are added on `finish`
+    resources_id: Ref,
+    page_ids: Vec<Ref>,
+    fonts: PdfFonts,
+}
+
+struct PdfCanvas<'a> {
+    content: Content,
+    fonts: &'a mut PdfFonts,
+}
+
+impl PdfDocument {
+    pub fn create<P: AsRef<Path>>(output_path: P) -> io::Result<Self> {
+        let mut next_id = Ref::new(1);
+        Ok(Self {
+            output_file: File::create(output_path)?,
+            pdf: Pdf::new(),
+            catalog_id: next_id.bump(),
+            pages_id: next_id.bump(),
+            resources_id: next_id.bump(),
+            next_id,
+            page_ids: Vec::new(),
+            fonts: PdfFonts::load()?,
+        })
+    }
+
+    fn alloc_id(&mut self) -> Ref {
+        self.next_id.bump()
+    }
+}
+
+impl PdfCanvas<'_> {
+    fn write_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        // Font is put in resources only when some glyph is drawn
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.content.begin_text().next_line(x, y);
+        for (run_font, run) in self.fonts.get_font_runs(font, text) {
+            let embedded_font = self.fonts.get_mut(run_font);
+            let resource_name = embedded_font.get_resource_name();
+            let encoded = embedded_font.encode_text(&run);
+            self.content
+                .set_font(Name(resource_name.as_bytes()), size)
+                .show(Str(&encoded));
+        }
+        self.content.end_text();
+        Ok(())
+    }
+}
+
+impl PatternCanvas for PdfCanvas<'_> {
+    fn set_fill_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        let [r, g, b] = color.0.map(|channel| channel as f32 / 255.0);
+        self.content.set_fill_rgb(r, g, b);
+        Ok(())
+    }
+
+    fn set_stroke_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        let [r, g, b] = color.0.map(|channel| channel as f32 / 255.0);
+        self.content.set_stroke_rgb(r, g, b);
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f32) -> io::Result<()> {
+        self.content.set_line_width(width);
+        Ok(())
+    }
+
+    fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> io::Result<()> {
+        self.content.rect(x, y, width, height);
+        Ok(())
+    }
+
+    fn circle(&mut self, x: f32, y: f32, radius: f32) -> io::Result<()> {
+        let dist = radius * CIRCLE_BEZIER_FACTOR;
+        self.content.move_to(x + radius, y);
+        // Four quarters counterclockwise, starting from the rightmost point
+        let quarters = [
+            [(x + radius, y + dist), (x + dist, y + radius), (x, y + radius)],
+            [(x - dist, y + radius), (x - radius, y + dist), (x - radius, y)],
+            [(x - radius, y - dist), (x - dist, y - radius), (x, y - radius)],
+            [(x + dist, y - radius), (x + radius, y - dist), (x + radius, y)],
+        ];
+        for [(x1, y1), (x2, y2), (x3, y3)] in quarters {
+            self.content.cubic_to(x1, y1, x2, y2, x3, y3);
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> io::Result<()> {
+        self.content.move_to(x1, y1).line_to(x2, y2);
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        self.content.fill_nonzero();
+        Ok(())
+    }
+
+    fn stroke(&mut self) -> io::Result<()> {
+        self.content.stroke();
+        Ok(())
+    }
+
+    fn left_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        self.write_text(x, y, font, size, text)
+    }
+
+    fn center_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        let width = self.fonts.get_text_width(font, size, text);
+        self.write_text(x - width / 2.0, y, font, size, text)
+    }
+
+    fn right_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        let width = self.fonts.get_text_width(font, size, text);
+        self.write_text(x - width, y, font, size, text)
+    }
+}
+
+impl PatternDocument for PdfDocument {
+    fn render_page<F>(&mut self, width: f32, height: f32, render_contents: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn PatternCanvas) -> io::Result<()>
+    {
+        let mut canvas = PdfCanvas {
+            content: Content::new(),
+            fonts: &mut self.fonts,
+        };
+        render_contents(&mut canvas)?;
+        let content = canvas.content.finish();
+
+        let content_id = self.alloc_id();
+        let page_id = self.alloc_id();
+        self.pdf.stream(content_id, &content);
+        self.pdf.page(page_id)
+            .parent(self.pages_id)
+            .media_box(Rect::new(0.0, 0.0, width, height))
+            .contents(content_id)
+            .pair(Name(b"Resources"), self.resources_id);
+        self.page_ids.push(page_id);
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let mut font_ids = Vec::new();
+        for embedded_font in PatternFont::ALL.into_iter().map(|font| self.fonts.get(font)) {
+            if embedded_font.is_used() {
+                font_ids.push((embedded_font.get_resource_name(), embedded_font.write(&mut self.pdf, &mut self.next_id)?));
+            }
+        }
+
+        let mut resources = self.pdf.indirect(self.resources_id).start::<Resources>();
+        let mut fonts = resources.fonts();
+        for (resource_name, font_id) in &font_ids {
+            fonts.pair(Name(resource_name.as_bytes()), *font_id);
+        }
+        // Writers close their dictionaries when dropped
+        drop(fonts);
+        drop(resources);
+
+        self.pdf.catalog(self.catalog_id).pages(self.pages_id);
+        self.pdf.pages(self.pages_id)
+            .kids(self.page_ids.iter().copied())
+            .count(self.page_ids.len() as i32);
+
+        self.output_file.write_all(&self.pdf.finish())?;
+        self.output_file.flush()
+    }
+}
+
+#[cfg(test)]
+mod test_pdf_backend {
+    use ab_glyph::{
+        Font,
+        FontRef
+    };
+    use image::Rgb;
+    use lopdf::Document;
+
+    use crate::backend::{
+        PatternDocument,
+        PatternFont
+    };
+
+    use super::PdfDocument;
+
+    #[test]
+    fn test_pdf_embeds_font_subsets() {
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_test_fonts.pdf");
+        let mut document = PdfDocument::create(&output_path).unwrap();
+        let result = document.render_page(100.0, 50.0, |canvas| {
+            canvas.set_fill_color(Rgb([255, 0, 0]))?;
+            canvas.circle(20.0, 20.0, 5.0)?;
+            canvas.fill()?;
+            canvas.left_text(10.0, 10.0, PatternFont::Sans, 8.0, "A(B)")?;
+            canvas.center_text(50.0, 20.0, PatternFont::SansBold, 8.0, "★λ")?;
+            canvas.right_text(90.0, 20.0, PatternFont::Mono, 8.0, "")
+        });
+        assert!(result.is_ok());
+        assert!(document.finish().is_ok());
+
+        let pdf = std::fs::read(&output_path).unwrap();
+        let pdf_text = String::from_utf8_lossy(&pdf);
+        assert!(pdf_text.starts_with("%PDF-1.7"));
+        // Only fonts with drawn glyphs are embedded
+        assert_eq!(pdf_text.matches("/Subtype /Type0").count(), 2);
+        assert_eq!(pdf_text.matches("/FontFile2").count(), 2);
+        assert!(!pdf_text.contains("/Type1"));
+        assert!(pdf_text.contains("/Encoding /Identity-H"));
+        assert!(pdf_text.contains("+DejaVuSans-Bold"));
+        assert!(!pdf_text.contains("DejaVuSansMono"));
+        // Glyphs of each font remapped after .notdef, mapped back to unicode
+        assert!(pdf_text.contains("<0002> <0028>"));
+        assert!(pdf_text.contains("<0001> <2605>"));
+        assert!(pdf_text.contains("<0002> <03BB>"));
+        assert!(pdf_text.trim_end().ends_with("%%EOF"));
+
+        // Each text object ends its own line
+        let document = Document::load_mem(&pdf).unwrap();
+        let text = document.extract_text(&[1]).unwrap();
+        assert_eq!(text.lines().collect::<Vec<_>>(), ["A(B)", "★λ"]);
+    }
+
+    #[test]
+    fn test_pdf_missing_glyph_drawn_in_sans() {
+        let mono = FontRef::try_from_slice(PatternFont::Mono.get_data()).unwrap();
+        let sans = FontRef::try_from_slice(PatternFont::Sans.get_data()).unwrap();
+        let missing = ['✿', '❀', '☯', '♛', '⚓', '☃', '⌘', '✈'].into_iter()
+            .find(|c| mono.glyph_id(*c).0 == 0 && sans.glyph_id(*c).0 != 0)
+            .unwrap();
+
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_test_fallback.pdf");
+        let mut document = PdfDocument::create(&output_path).unwrap();
+        let text = format!("A{missing}");
+        let result = document.render_page(100.0, 50.0, |canvas| {
+            canvas.center_text(50.0, 20.0, PatternFont::Mono, 8.0, &text)
+        });
+        assert!(result.is_ok());
+        assert!(document.finish().is_ok());
+
+        let pdf = std::fs::read(&output_path).unwrap();
+        let pdf_text = String::from_utf8_lossy(&pdf);
+        assert_eq!(pdf_text.matches("/Subtype /Type0").count(), 2);
+        assert!(pdf_text.contains("+DejaVuSansMono"));
+        // Missing glyph is the only one in sans subset
+        assert!(pdf_text.contains(&format!("<0001> <{:04X}>", missing as u32)));
+
+        let document = Document::load_mem(&pdf).unwrap();
+        assert_eq!(document.extract_text(&[1]).unwrap().trim_end(), text);
+    }
+
+    #[test]
+    fn test_pdf_xref_and_page_tree() {
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_test_pages.pdf");
+        let mut document = PdfDocument::create(&output_path).unwrap();
+        for width in [100.0, 200.0, 300.0] {
+            let result = document.render_page(width, 50.0, |canvas| {
+                canvas.left_text(10.0, 10.0, PatternFont::Mono, 8.0, "page")
+            });
+            assert!(result.is_ok());
+        }
+        assert!(document.finish().is_ok());
+
+        // Every used xref entry points at its object, offsets are in bytes
+        let pdf = std::fs::read(&output_path).unwrap();
+        let trailer_start = pdf.windows(9).rposition(|window| window == b"startxref").unwrap();
+        let trailer = String::from_utf8_lossy(&pdf[trailer_start..]);
+        let startxref = trailer.split_whitespace().nth(1).unwrap().parse::<usize>().unwrap();
+        let xref = String::from_utf8_lossy(&pdf[startxref..trailer_start]);
+        assert!(xref.starts_with("xref"));
+        let xref_entries = xref.lines()
+            .skip(3)
+            .take_while(|line| !line.starts_with("trailer"))
+            .collect::<Vec<_>>();
+        assert!(!xref_entries.is_empty());
+        for (idx, entry) in xref_entries.iter().enumerate() {
+            let offset = entry[..10].parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj", idx + 1).as_bytes()), "{entry}");
+        }
+
+        let document = Document::load_mem(&pdf).unwrap();
+        let pages = document.get_pages();
+        assert_eq!(pages.len(), 3);
+        for (page_number, width) in [(1, 100), (2, 200), (3, 300)] {
+            let page = document.get_dictionary(pages[&page_number]).unwrap();
+            let media_box = page.get(b"MediaBox").unwrap().as_array().unwrap();
+            assert_eq!(media_box[2].as_float().unwrap() as i32, width);
+        }
+    }
+
+    #[test]
+    fn test_pdf_create_fails_on_bad_path() {
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_no_such_dir").join("pattern.pdf");
+        assert!(PdfDocument::create(output_path).is_err());
+    }
+}
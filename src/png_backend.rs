@@ -0,0 +1,358 @@
+use std::{
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use ab_glyph::{
+    point,
+    Font,
+    FontRef,
+    ScaleFont
+};
+use image::{
+    Rgb,
+    RgbImage
+};
+
+use crate::backend::{
+    get_numbered_page_path,
+    GraphicsState,
+    PathShape,
+    PatternCanvas,
+    PatternDocument,
+    PatternFont
+};
+
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// Same DejaVu faces as embedded in PDF
+struct PngFonts {
+    sans: FontRef<'static>,
+    sans_bold: FontRef<'static>,
+    mono: FontRef<'static>,
+    mono_bold: FontRef<'static>,
+    serif: FontRef<'static>,
+    serif_bold: FontRef<'static>,
+}
+
+impl PngFonts {
+    fn load() -> io::Result<Self> {
+        let load_font = |data: &'static [u8]| FontRef::try_from_slice(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+
+        Ok(Self {
+            sans: load_font(PatternFont::Sans.get_data())?,
+            sans_bold: load_font(PatternFont::SansBold.get_data())?,
+            mono: load_font(PatternFont::Mono.get_data())?,
+            mono_bold: load_font(PatternFont::MonoBold.get_data())?,
+            serif: load_font(PatternFont::Serif.get_data())?,
+            serif_bold: load_font(PatternFont::SerifBold.get_data())?,
+        })
+    }
+
+    fn get(&self, font: PatternFont) -> &FontRef<'static> {
+        match font {
+            PatternFont::Sans => &self.sans,
+            PatternFont::SansBold => &self.sans_bold,
+            PatternFont::Mono => &self.mono,
+            PatternFont::MonoBold => &self.mono_bold,
+            PatternFont::Serif => &self.serif,
+            PatternFont::SerifBold => &self.serif_bold,
+        }
+    }
+}
+
+/// Every page is written to its own PNG file in given resolution
+pub struct PngDocument {
+    output_path: PathBuf,
+    dpi: f32,
+    pages_count: usize,
+    fonts: PngFonts,
+}
+
+struct PngCanvas<'a> {
+    img: RgbImage,
+    pixels_per_point: f32,
+    state: GraphicsState,
+    fonts: &'a PngFonts,
+}
+
+impl PngDocument {
+    pub fn create<P: AsRef<Path>>(output_path: P, dpi: f32) -> io::Result<Self> {
+        Ok(Self {
+            output_path: output_path.as_ref().to_path_buf(),
+            dpi,
+            pages_count: 0,
+            fonts: PngFonts::load()?
+        })
+    }
+}
+
+fn blend(background: &Rgb<u8>, color: Rgb<u8>, coverage: f32) -> Rgb<u8> {
+    let coverage = coverage.clamp(0.0, 1.0);
+    Rgb([0, 1, 2].map(|idx| {
+        (background.0[idx] as f32 * (1.0 - coverage) + color.0[idx] as f32 * coverage).round() as u8
+    }))
+}
+
+impl PngCanvas<'_> {
+    /// Page points to image pixels, y axis flipped
+    fn to_pixels(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.pixels_per_point, self.img.height() as f32 - y * self.pixels_per_point)
+    }
+
+    /// Paints pixels with centers matching `is_inside`, bounds in pixels
+    fn paint_region<F>(&mut self, min: (f32, f32), max: (f32, f32), color: Rgb<u8>, is_inside: F)
+    where
+        F: Fn(f32, f32) -> bool
+    {
+        let (width, height) = self.img.dimensions();
+        let x_range = (min.0.floor().max(0.0) as u32)..(max.0.ceil().max(0.0) as u32).min(width);
+        let y_range = (min.1.floor().max(0.0) as u32)..(max.1.ceil().max(0.0) as u32).min(height);
+
+        for py in y_range {
+            for px in x_range.clone() {
+                if is_inside(px as f32 + 0.5, py as f32 + 0.5) {
+                    self.img.put_pixel(px, py, color);
+                }
+            }
+        }
+    }
+
+    fn fill_shape(&mut self, shape: PathShape, color: Rgb<u8>) {
+        match shape {
+            PathShape::Rectangle { x, y, width, height } => {
+                let (x0, y0) = self.to_pixels(x, y + height);
+                let (x1, y1) = self.to_pixels(x + width, y);
+                self.paint_region((x0, y0), (x1, y1), color, |px, py| px >= x0 && px < x1 && py >= y0 && py < y1);
+            },
+            PathShape::Circle { x, y, radius } => {
+                let (cx, cy) = self.to_pixels(x, y);
+                let radius = radius * self.pixels_per_point;
+                self.paint_region((cx - radius, cy - radius), (cx + radius, cy + radius), color, |px, py| {
+                    (px - cx).hypot(py - cy) <= radius
+                });
+            },
+            PathShape::Line { .. } => {},
+        }
+    }
+
+    fn stroke_segment(&mut self, from: (f32, f32), to: (f32, f32), color: Rgb<u8>) {
+        // Thin lines are kept visible
+        let half_width = (self.state.line_width * self.pixels_per_point).max(1.0) / 2.0;
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length_squared = (dx * dx + dy * dy).max(f32::EPSILON);
+
+        self.paint_region(
+            (from.0.min(to.0) - half_width, from.1.min(to.1) - half_width),
+            (from.0.max(to.0) + half_width, from.1.max(to.1) + half_width),
+            color,
+            |px, py| {
+                let t = (((px - from.0) * dx + (py - from.1) * dy) / length_squared).clamp(0.0, 1.0);
+                (px - from.0 - t * dx).hypot(py - from.1 - t * dy) <= half_width
+            }
+        );
+    }
+
+    fn stroke_shape(&mut self, shape: PathShape, color: Rgb<u8>) {
+        match shape {
+            PathShape::Rectangle { x, y, width, height } => {
+                let corners = [(x, y), (x + width, y), (x + width, y + height), (x, y + height)]
+                    .map(|(x, y)| self.to_pixels(x, y));
+                (0..4).for_each(|idx| self.stroke_segment(corners[idx], corners[(idx + 1) % 4], color));
+            },
+            PathShape::Circle { x, y, radius } => {
+                let (cx, cy) = self.to_pixels(x, y);
+                let radius = radius * self.pixels_per_point;
+                let half_width = (self.state.line_width * self.pixels_per_point).max(1.0) / 2.0;
+                let outer = radius + half_width;
+                self.paint_region((cx - outer, cy - outer), (cx + outer, cy + outer), color, |px, py| {
+                    ((px - cx).hypot(py - cy) - radius).abs() <= half_width
+                });
+            },
+            PathShape::Line { x1, y1, x2, y2 } => {
+                let from = self.to_pixels(x1, y1);
+                let to = self.to_pixels(x2, y2);
+                self.stroke_segment(from, to, color);
+            },
+        }
+    }
+
+    fn get_text_width(&self, font: PatternFont, size: f32, text: &str) -> f32 {
+        let Some(scale) = self.fonts.get(font).pt_to_px_scale(self.get_font_size(size)) else {
+            return 0.0;
+        };
+        let scaled_font = self.fonts.get(font).as_scaled(scale);
+        text.chars()
+            .map(|c| scaled_font.h_advance(scaled_font.glyph_id(c)))
+            .sum()
+    }
+
+    /// Size suitable for `pt_to_px_scale`, which assumes 96 DPI
+    fn get_font_size(&self, size: f32) -> f32 {
+        size * self.pixels_per_point * POINTS_PER_INCH / 96.0
+    }
+
+    fn draw_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        let font_size = self.get_font_size(size);
+        let (mut caret_x, baseline_y) = self.to_pixels(x, y);
+        let color = self.state.fill_color;
+
+        for c in text.chars() {
+            // Fallback for glyphs missing in replacement font
+            let font = match self.fonts.get(font).glyph_id(c).0 {
+                0 => &self.fonts.sans,
+                _ => self.fonts.get(font),
+            };
+            let Some(scale) = font.pt_to_px_scale(font_size) else {
+                continue;
+            };
+            let scaled_font = font.as_scaled(scale);
+            let glyph_id = scaled_font.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(scale, point(caret_x, baseline_y));
+            caret_x += scaled_font.h_advance(glyph_id);
+
+            if let Some(outlined_glyph) = font.outline_glyph(glyph) {
+                let bounds = outlined_glyph.px_bounds();
+                let img = &mut self.img;
+                outlined_glyph.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i64 + gx as i64;
+                    let py = bounds.min.y as i64 + gy as i64;
+                    if px >= 0 && py >= 0 && px < img.width() as i64 && py < img.height() as i64 {
+                        let pixel = img.get_pixel_mut(px as u32, py as u32);
+                        *pixel = blend(pixel, color, coverage);
+                    }
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PatternCanvas for PngCanvas<'_> {
+    fn set_fill_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        self.state.fill_color = color;
+        Ok(())
+    }
+
+    fn set_stroke_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        self.state.stroke_color = color;
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f32) -> io::Result<()> {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Rectangle { x, y, width, height });
+        Ok(())
+    }
+
+    fn circle(&mut self, x: f32, y: f32, radius: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Circle { x, y, radius });
+        Ok(())
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Line { x1, y1, x2, y2 });
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let color = self.state.fill_color;
+        std::mem::take(&mut self.state.path).into_iter()
+            .for_each(|shape| self.fill_shape(shape, color));
+        Ok(())
+    }
+
+    fn stroke(&mut self) -> io::Result<()> {
+        let color = self.state.stroke_color;
+        std::mem::take(&mut self.state.path).into_iter()
+            .for_each(|shape| self.stroke_shape(shape, color));
+        Ok(())
+    }
+
+    fn left_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        self.draw_text(x, y, font, size, text)
+    }
+
+    fn center_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        let width = self.get_text_width(font, size, text) / self.pixels_per_point;
+        self.draw_text(x - width / 2.0, y, font, size, text)
+    }
+
+    fn right_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        let width = self.get_text_width(font, size, text) / self.pixels_per_point;
+        self.draw_text(x - width, y, font, size, text)
+    }
+}
+
+impl PatternDocument for PngDocument {
+    fn render_page<F>(&mut self, width: f32, height: f32, render_contents: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn PatternCanvas) -> io::Result<()>
+    {
+        let pixels_per_point = self.dpi / POINTS_PER_INCH;
+        let mut canvas = PngCanvas {
+            img: RgbImage::from_pixel(
+                (width * pixels_per_point).round() as u32,
+                (height * pixels_per_point).round() as u32,
+                Rgb([255, 255, 255])
+            ),
+            pixels_per_point,
+            state: GraphicsState::default(),
+            fonts: &self.fonts
+        };
+        render_contents(&mut canvas)?;
+
+        self.pages_count += 1;
+        canvas.img.save(get_numbered_page_path(&self.output_path, self.pages_count))
+            .map_err(io::Error::other)
+    }
+
+    fn finish(self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_png_backend {
+    use image::Rgb;
+
+    use crate::backend::{
+        PatternDocument,
+        PatternFont
+    };
+
+    use super::PngDocument;
+
+    #[test]
+    fn test_png_page_content() {
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_test.png");
+        let mut document = PngDocument::create(&output_path, 144.0).unwrap();
+        let result = document.render_page(100.0, 50.0, |canvas| {
+            canvas.set_fill_color(Rgb([255, 0, 0]))?;
+            canvas.rectangle(10.0, 10.0, 20.0, 5.0)?;
+            canvas.fill()?;
+            canvas.set_fill_color(Rgb([0, 0, 0]))?;
+            canvas.center_text(70.0, 20.0, PatternFont::MonoBold, 12.0, "★A")
+        });
+        assert!(result.is_ok());
+        assert!(document.finish().is_ok());
+
+        let img = image::open(std::env::temp_dir().join("diamonds_imager_generator_test_1.png"))
+            .unwrap()
+            .to_rgb8();
+        // 2 pixels per point, y axis flipped
+        assert_eq!(img.dimensions(), (200, 100));
+        assert_eq!(*img.get_pixel(40, 75), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(5, 5), Rgb([255, 255, 255]));
+        assert!(img.pixels().any(|px| *px == Rgb([0, 0, 0])));
+    }
+}
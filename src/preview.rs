@@ -0,0 +1,212 @@
+use image::{
+    Rgb,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    mask::CellsMask,
+    types::DiamondShape
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewOptions {
+    /// Drill cell size in preview, 1 saves plain pixel per drill image
+    pub pixels_per_drill: u32,
+    /// Canvas adhesive visible between drills
+    pub background_color: [u8; 3],
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            pixels_per_drill: 12,
+            background_color: [236, 232, 224]
+        }
+    }
+}
+
+/// Light comes from top left corner, image y axis points down
+const LIGHT_DIRECTION: (f32, f32) = (-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2);
+const FACET_SHADE_STRENGTH: f32 = 0.35;
+const SHADOW_STRENGTH: f32 = 0.45;
+
+/// Look of single pixel of drill cell, same for every drill of given shape
+#[derive(Debug, Clone, Copy)]
+struct DrillPixel {
+    /// Antialiased drill edge
+    coverage: f32,
+    /// Color multiplier of facet under the pixel
+    shade: f32,
+    /// Blend towards white
+    highlight: f32,
+    /// Background darkening
+    shadow: f32,
+}
+
+fn facet_shade(normal: (f32, f32)) -> f32 {
+    1.0 + FACET_SHADE_STRENGTH * (normal.0 * LIGHT_DIRECTION.0 + normal.1 * LIGHT_DIRECTION.1)
+}
+
+/// Round drills have flat table surrounded by 8 facets,
+/// square drills have table surrounded by 4 bevels
+fn get_drill_pixel(diamond_shape: &DiamondShape, cell_size: f32, x: f32, y: f32) -> DrillPixel {
+    let center = cell_size / 2.0;
+    let (dx, dy) = (x - center, y - center);
+    let shadow_offset = cell_size * 0.07;
+    let (sdx, sdy) = (dx - shadow_offset, dy - shadow_offset);
+    let shadow_softness = (cell_size * 0.06).max(1.0);
+
+    // Distance to drill center in shape's metric, table and edge in the same units
+    let (distance, shadow_distance, half_size, facet_normal) = match diamond_shape {
+        DiamondShape::Round { .. } => {
+            let facet_angle = std::f32::consts::FRAC_PI_4;
+            let facet_idx = (dy.atan2(dx) / facet_angle).round();
+            (
+                dx.hypot(dy),
+                sdx.hypot(sdy),
+                cell_size * 0.45,
+                ((facet_idx * facet_angle).cos(), (facet_idx * facet_angle).sin())
+            )
+        },
+        DiamondShape::Square { .. } => (
+            dx.abs().max(dy.abs()),
+            sdx.abs().max(sdy.abs()),
+            cell_size * 0.46,
+            if dx.abs() > dy.abs() { (dx.signum(), 0.0) } else { (0.0, dy.signum()) }
+        ),
+    };
+    let table_half_size = half_size * 0.55;
+
+    let highlight_radius = half_size * 0.22;
+    let highlight_distance = (dx + half_size * 0.35).hypot(dy + half_size * 0.35);
+
+    DrillPixel {
+        coverage: (half_size - distance + 0.5).clamp(0.0, 1.0),
+        shade: if distance <= table_half_size { 1.0 } else { facet_shade(facet_normal) },
+        highlight: 0.8 * (1.0 - highlight_distance / highlight_radius).clamp(0.0, 1.0).powi(2),
+        shadow: ((half_size - shadow_distance) / shadow_softness + 0.5).clamp(0.0, 1.0),
+    }
+}
+
+fn shade_drill_pixel(drill_color: &Rgb<u8>, background_color: &[u8; 3], drill_pixel: &DrillPixel) -> Rgb<u8> {
+    Rgb([0, 1, 2].map(|idx| {
+        let background = background_color[idx] as f32 * (1.0 - SHADOW_STRENGTH * drill_pixel.shadow);
+        let facet = (drill_color.0[idx] as f32 * drill_pixel.shade).min(255.0);
+        let drill = facet + (255.0 - facet) * drill_pixel.highlight;
+        (background + (drill - background) * drill_pixel.coverage).round() as u8
+    }))
+}
+
+/// Finished canvas look, every drill scaled to `pixels_per_drill` square, empty cells show bare canvas
+pub fn render_realistic_preview(
+    dithered_img: &RgbImage,
+    cells_mask: &CellsMask,
+    diamond_shape: &DiamondShape,
+    preview_options: &PreviewOptions
+) -> RgbImage {
+    let background_color = Rgb(preview_options.background_color);
+    let cell_size = preview_options.pixels_per_drill.max(1);
+    if cell_size == 1 {
+        return RgbImage::from_fn(dithered_img.width(), dithered_img.height(), |x, y| {
+            if cells_mask.is_empty_cell(x, y) { background_color } else { *dithered_img.get_pixel(x, y) }
+        });
+    }
+
+    let drill_template = (0..cell_size * cell_size)
+        .map(|idx| get_drill_pixel(
+            diamond_shape,
+            cell_size as f32,
+            (idx % cell_size) as f32 + 0.5,
+            (idx / cell_size) as f32 + 0.5
+        ))
+        .collect::<Vec<_>>();
+
+    RgbImage::from_fn(dithered_img.width() * cell_size, dithered_img.height() * cell_size, |x, y| {
+        if cells_mask.is_empty_cell(x / cell_size, y / cell_size) {
+            return background_color;
+        }
+
+        let drill_color = dithered_img.get_pixel(x / cell_size, y / cell_size);
+        let drill_pixel = &drill_template[((y % cell_size) * cell_size + x % cell_size) as usize];
+        shade_drill_pixel(drill_color, &preview_options.background_color, drill_pixel)
+    })
+}
+
+#[cfg(test)]
+mod test_preview {
+    use image::{
+        GrayImage,
+        Luma,
+        Rgb,
+        RgbImage
+    };
+
+    use crate::{
+        mask::CellsMask,
+        types::DiamondShape
+    };
+
+    use super::{
+        render_realistic_preview,
+        PreviewOptions
+    };
+
+    const RED: Rgb<u8> = Rgb([200, 30, 30]);
+
+    #[test]
+    fn test_preview_scaled_drills() {
+        let img = RgbImage::from_pixel(3, 2, RED);
+        let options = PreviewOptions::default();
+        let preview = render_realistic_preview(&img, &CellsMask::default(), &DiamondShape::common_round(), &options);
+
+        assert_eq!(preview.dimensions(), (3 * options.pixels_per_drill, 2 * options.pixels_per_drill));
+        // Drill table in the middle of cell, background in the corner
+        let center = options.pixels_per_drill / 2;
+        assert_eq!(*preview.get_pixel(center + 1, center + 1), RED);
+        assert_eq!(*preview.get_pixel(0, 0), Rgb(options.background_color));
+    }
+
+    #[test]
+    fn test_preview_facets_and_shadow() {
+        let img = RgbImage::from_pixel(1, 1, RED);
+        let options = PreviewOptions {
+            pixels_per_drill: 40,
+            ..Default::default()
+        };
+
+        for diamond_shape in [DiamondShape::common_round(), DiamondShape::common_square()] {
+            let preview = render_realistic_preview(&img, &CellsMask::default(), &diamond_shape, &options);
+            // Lit top left facet is brighter than bottom right one
+            assert!(preview.get_pixel(5, 20).0[0] > preview.get_pixel(35, 20).0[0]);
+            // Shadow falls on bottom right
+            assert!(preview.get_pixel(39, 30).0[0] < preview.get_pixel(0, 9).0[0]);
+        }
+    }
+
+    #[test]
+    fn test_preview_single_pixel_per_drill() {
+        let img = RgbImage::from_pixel(4, 4, RED);
+        let preview = render_realistic_preview(&img, &CellsMask::default(), &DiamondShape::common_square(), &PreviewOptions {
+            pixels_per_drill: 1,
+            ..Default::default()
+        });
+        assert_eq!(preview, img);
+    }
+
+    #[test]
+    fn test_preview_empty_cells() {
+        let img = RgbImage::from_pixel(2, 1, RED);
+        let cells_mask = CellsMask::from_coverage(&GrayImage::from_fn(2, 1, |x, _| Luma([x as u8 * 255])));
+        let options = PreviewOptions::default();
+        let preview = render_realistic_preview(&img, &cells_mask, &DiamondShape::common_square(), &options);
+
+        let center = options.pixels_per_drill / 2;
+        assert_eq!(*preview.get_pixel(center, center), Rgb(options.background_color));
+        assert_eq!(*preview.get_pixel(options.pixels_per_drill + center + 1, center + 1), RED);
+    }
+}
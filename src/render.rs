@@ -4,20 +4,42 @@ use image::{
     RgbImage
 };
 use millimeter::mm;
-use pdf_canvas::{
-    graphicsstate::Color, 
-    BuiltinFont, 
-    Canvas, 
-    Pdf
+use serde::{
+    Deserialize, 
+    Serialize
 };
 use crate::{
-    dmc::ImageDmcLegend, 
+    backend::{
+        PatternCanvas, 
+        PatternDocument, 
+        PatternFont
+    }, 
+    colors::get_contrasting_black_or_white, 
+    dmc::{
+        DrillBagsConfig, 
+        ImageDmcLegend
+    }, 
+    mask::CellsMask, 
+    pdf_backend::PdfDocument, 
+    png_backend::PngDocument, 
+    style::RenderStyle, 
+    svg_backend::SvgDocument, 
+    symbols::get_symbol_font, 
+    tiling::{
+        get_row_label, 
+        get_tile_pattern_area_rect, 
+        Tile, 
+        TileLayout
+    }, 
     types::{
         DiamondShape, 
         PaperSheet, 
         Pos2D, 
+        Pos2U, 
         Rect2D, 
-        Size2D
+        Rect2U, 
+        Size2D, 
+        Size2U
     }
 };
 
@@ -39,9 +61,9 @@ fn rect_to_points(rect: &Rect2D) -> (f32, f32, f32, f32) {
 }
 
 fn draw_filled_rect(
-    canvas: &mut Canvas,
+    canvas: &mut dyn PatternCanvas,
     rect: &Rect2D,
-    filling_color: Color
+    filling_color: Rgb<u8>
 ) -> std::io::Result<()> {
     let (x, y, w, h) = rect_to_points(rect);
     canvas.set_fill_color(filling_color)?;
@@ -50,10 +72,10 @@ fn draw_filled_rect(
 }
 
 fn draw_empty_bordered_rect(
-    canvas: &mut Canvas,
+    canvas: &mut dyn PatternCanvas,
     rect: &Rect2D,
     line_thickness_pt: f32,
-    border_color: Color
+    border_color: Rgb<u8>
 ) -> std::io::Result<()> {
     let (x, y, w, h) = rect_to_points(rect);
     canvas.set_line_width(line_thickness_pt)?;
@@ -62,101 +84,901 @@ fn draw_empty_bordered_rect(
     canvas.stroke()
 }
 
-pub fn render_diamond_painting_project(
-    paper_sheet: PaperSheet,
-    diamond_shape: DiamondShape,
-    dmc_image_legend: ImageDmcLegend,
-    dithered_img: RgbImage,
-    draw_template_lines: bool,
-    output_path: &str,
+/// Drill fills the whole cell, so neighbouring drills touch like on finished canvas
+fn draw_drill(
+    canvas: &mut dyn PatternCanvas,
+    diamond_shape: &DiamondShape,
+    cell_rect: &Rect2D,
+    filling_color: Rgb<u8>
 ) -> std::io::Result<()> {
-    const TEMPLATE_LINES_THICKNESS_PT: f32 = 0.75;
+    match diamond_shape {
+        DiamondShape::Square { .. } => draw_filled_rect(canvas, cell_rect, filling_color),
+        DiamondShape::Round { .. } => {
+            let (x, y, w, h) = rect_to_points(cell_rect);
+            canvas.set_fill_color(filling_color)?;
+            canvas.circle(x + w / 2.0, y + h / 2.0, w.min(h) / 2.0)?;
+            canvas.fill()
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PageLayout {
+    SinglePage,
+    Tiled {
+        overlap_cells: u32
+    },
+}
+
+/// Black or white symbol, outlined with the other one when contrast is below style's minimum
+fn get_symbol_colors(background: &ColorRGB, render_style: &RenderStyle) -> (Rgb<u8>, Option<Rgb<u8>>) {
+    let (symbol_color, contrast) = get_contrasting_black_or_white(background);
+    let outline_color = render_style.symbol_outline_min_contrast
+        .filter(|min_contrast| contrast < *min_contrast)
+        .map(|_| Rgb(symbol_color.0.map(|channel| 255 - channel)));
+    (Rgb(symbol_color.0), outline_color)
+}
+
+/// Outline is made of symbol copies shifted around in outline color, colors as from `get_symbol_colors`
+fn draw_symbol(
+    canvas: &mut dyn PatternCanvas,
+    center_x: f32,
+    baseline_y: f32,
+    font: PatternFont,
+    font_size_pt: f32,
+    symbol: &str,
+    (symbol_color, outline_color): (Rgb<u8>, Option<Rgb<u8>>)
+) -> std::io::Result<()> {
+    const OUTLINE_DIRECTIONS: [(f32, f32); 8] = [
+        (-1.0, -1.0), (0.0, -1.0), (1.0, -1.0),
+        (-1.0, 0.0), (1.0, 0.0),
+        (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0),
+    ];
+
+    if let Some(outline_color) = outline_color {
+        let outline_width = font_size_pt * 0.06;
+        canvas.set_fill_color(outline_color)?;
+        OUTLINE_DIRECTIONS.iter().try_for_each(|(dx, dy)| canvas.center_text(
+            center_x + dx * outline_width,
+            baseline_y + dy * outline_width,
+            font,
+            font_size_pt,
+            symbol
+        ))?;
+    }
 
-    let mut document = Pdf::create(output_path)
-        .expect("Create pdf file");
+    canvas.set_fill_color(symbol_color)?;
+    canvas.center_text(center_x, baseline_y, font, font_size_pt, symbol)
+}
+
+/// Which pattern pages are put in PDF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternPages {
+    #[default]
+    Color,
+    /// Black symbols on white with grid lines, saves ink
+    Symbols,
+    /// Color pages followed by symbols pages
+    ColorAndSymbols,
+}
 
-    // Use builtin font
-    let font = BuiltinFont::Courier_Bold;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PatternPageStyle {
+    Color,
+    Symbols,
+}
+
+impl PatternPages {
+    fn get_styles(&self) -> &'static [PatternPageStyle] {
+        match self {
+            PatternPages::Color => &[PatternPageStyle::Color],
+            PatternPages::Symbols => &[PatternPageStyle::Symbols],
+            PatternPages::ColorAndSymbols => &[PatternPageStyle::Color, PatternPageStyle::Symbols],
+        }
+    }
 
+    /// Symbols pages always have grid with rulers
+    pub fn has_grid_lines(&self) -> bool {
+        self.get_styles().contains(&PatternPageStyle::Symbols)
+    }
+}
+
+fn draw_page_header(
+    canvas: &mut dyn PatternCanvas,
+    paper_sheet: &PaperSheet,
+    render_style: &RenderStyle,
+    text: &str
+) -> std::io::Result<()> {
     let printing_area_rect = paper_sheet.get_printing_area_rect();
-    let img_size = Size2D {
-        w: dithered_img.width() as f32 * diamond_shape.get_size(),
-        h: dithered_img.height() as f32 * diamond_shape.get_size(),
-    };
-    let image_occupied_area_rect = printing_area_rect.get_centered(&img_size);
+    let text_baseline = printing_area_rect.top() - render_style.get_page_header_height() / 2.0;
 
-    // Painting image
+    canvas.set_fill_color(Rgb([0, 0, 0]))?;
+    canvas.left_text(
+        mm_to_points(printing_area_rect.left()),
+        mm_to_points(text_baseline),
+        render_style.header_font.into(),
+        render_style.header_font_size_pt,
+        text
+    )?;
+
+    match &render_style.header_text {
+        Some(header_text) => canvas.right_text(
+            mm_to_points(printing_area_rect.right()),
+            mm_to_points(text_baseline),
+            render_style.text_font.into(),
+            render_style.header_font_size_pt,
+            header_text
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Footer text is placed in the middle of bottom margin
+fn draw_page_footer(
+    canvas: &mut dyn PatternCanvas,
+    paper_sheet: &PaperSheet,
+    render_style: &RenderStyle
+) -> std::io::Result<()> {
+    match &render_style.footer_text {
+        Some(footer_text) => {
+            canvas.set_fill_color(Rgb([0, 0, 0]))?;
+            canvas.center_text(
+                mm_to_points(paper_sheet.size.w / 2.0),
+                mm_to_points(paper_sheet.print_margins.bottom / 2.0) - render_style.footer_font_size_pt / 3.0,
+                render_style.text_font.into(),
+                render_style.footer_font_size_pt,
+                footer_text
+            )
+        },
+        None => Ok(()),
+    }
+}
+
+/// Renders page of paper sheet size, with style's footer
+fn render_styled_page<D, F>(
+    document: &mut D,
+    paper_sheet: &PaperSheet,
+    render_style: &RenderStyle,
+    render_contents: F
+) -> std::io::Result<()>
+where
+    D: PatternDocument,
+    F: FnOnce(&mut dyn PatternCanvas) -> std::io::Result<()>
+{
     document.render_page(
         mm_to_points(paper_sheet.size.w),
         mm_to_points(paper_sheet.size.h),
         |canvas| {
-            
-            if draw_template_lines {
-                // Margins
-                draw_empty_bordered_rect(
-                    canvas, 
-                    &printing_area_rect, 
-                    TEMPLATE_LINES_THICKNESS_PT,
-                    Color::rgb(255, 0, 0)
-                )?;
+            render_contents(canvas)?;
+            draw_page_footer(canvas, paper_sheet, render_style)
+        })
+}
 
-                // Occupied area
-                draw_empty_bordered_rect(
-                    canvas, 
-                    &image_occupied_area_rect, 
-                    TEMPLATE_LINES_THICKNESS_PT,
-                    Color::rgb(0, 255, 0)
-                )?;
-            }
+fn draw_template_frames(
+    canvas: &mut dyn PatternCanvas,
+    render_style: &RenderStyle,
+    printing_area_rect: &Rect2D,
+    image_occupied_area_rect: &Rect2D
+) -> std::io::Result<()> {
+    // Margins
+    if render_style.show_margins_frame {
+        draw_empty_bordered_rect(
+            canvas, 
+            printing_area_rect, 
+            render_style.frame_line_width_pt,
+            Rgb(render_style.margins_frame_color)
+        )?;
+    }
+
+    // Occupied area
+    if render_style.show_occupied_area_frame {
+        draw_empty_bordered_rect(
+            canvas, 
+            image_occupied_area_rect, 
+            render_style.frame_line_width_pt,
+            Rgb(render_style.occupied_area_frame_color)
+        )?;
+    }
+
+    Ok(())
+}
+
+fn draw_line(
+    canvas: &mut dyn PatternCanvas,
+    from: Pos2D,
+    to: Pos2D,
+    line_thickness_pt: f32,
+    line_color: Rgb<u8>
+) -> std::io::Result<()> {
+    canvas.set_line_width(line_thickness_pt)?;
+    canvas.set_stroke_color(line_color)?;
+    canvas.line(
+        mm_to_points(from.x),
+        mm_to_points(from.y),
+        mm_to_points(to.x),
+        mm_to_points(to.y)
+    )?;
+    canvas.stroke()
+}
+
+/// Cell borders with thicker line every 10 cells, indices printed on rulers around the grid.
+/// Lines and labels follow image coordinates, so neighbouring tiles match
+fn draw_grid(
+    canvas: &mut dyn PatternCanvas,
+    render_style: &RenderStyle,
+    diamond_shape: &DiamondShape,
+    cells: &Rect2U,
+    area_rect: &Rect2D,
+) -> std::io::Result<()> {
+    const BLOCK_CELLS: u32 = 10;
+
+    let cell_size = diamond_shape.get_size();
+    let grid_rect = Rect2D {
+        pos: area_rect.pos,
+        size: Size2D {
+            w: cells.size.w as f32 * cell_size,
+            h: cells.size.h as f32 * cell_size,
+        }
+    };
+    let line_thickness = |idx: u32| if idx.is_multiple_of(BLOCK_CELLS) { 
+        render_style.grid_block_line_width_pt 
+    } else { 
+        render_style.grid_cell_line_width_pt 
+    };
+    let line_color = Rgb(render_style.grid_line_color);
+    let ruler_font: PatternFont = render_style.text_font.into();
+
+    for x in cells.pos.x..=cells.right() {
+        let line_x = grid_rect.left() + (x - cells.pos.x) as f32 * cell_size;
+        draw_line(
+            canvas,
+            Pos2D { x: line_x, y: grid_rect.bottom() },
+            Pos2D { x: line_x, y: grid_rect.top() },
+            line_thickness(x),
+            line_color
+        )?;
+    }
+
+    for y in cells.pos.y..=cells.bottom() {
+        let line_y = grid_rect.top() - (y - cells.pos.y) as f32 * cell_size;
+        draw_line(
+            canvas,
+            Pos2D { x: grid_rect.left(), y: line_y },
+            Pos2D { x: grid_rect.right(), y: line_y },
+            line_thickness(y),
+            line_color
+        )?;
+    }
 
-            // Diamonds
-            let flip_y = dithered_img.height();
-            let symbol_font_size = mm_to_points(mm::new(2.2));
-            let symbol_x_oiffset = mm_to_points(diamond_shape.get_size()) / 2.0;
-            let symbol_y_oiffset = mm_to_points(diamond_shape.get_size()) / 4.0;
+    // 1-based indices of first cell and every `ruler_label_interval`th one
+    let label_interval = render_style.ruler_label_interval.max(1);
+    let is_labeled = |idx: u32| idx == 0 || (idx + 1).is_multiple_of(label_interval);
+    let ruler_gap = mm::new(render_style.ruler_gap_mm);
+    let text_center_offset = render_style.ruler_font_size_pt / 3.0;
+    canvas.set_fill_color(Rgb([0, 0, 0]))?;
 
-            dithered_img.enumerate_pixels()
-                .try_for_each(|(x, y, pixel)| {
-                    let pixel_rect = Rect2D {
+    for x in (cells.pos.x..cells.right()).filter(|x| is_labeled(*x)) {
+        let label = (x + 1).to_string();
+        let center_x = mm_to_points(grid_rect.left() + (x - cells.pos.x) as f32 * cell_size + cell_size / 2.0);
+        canvas.center_text(center_x, mm_to_points(grid_rect.top() + ruler_gap), ruler_font, render_style.ruler_font_size_pt, &label)?;
+        canvas.center_text(center_x, mm_to_points(grid_rect.bottom() - ruler_gap) - 2.0 * text_center_offset, ruler_font, render_style.ruler_font_size_pt, &label)?;
+    }
+
+    for y in (cells.pos.y..cells.bottom()).filter(|y| is_labeled(*y)) {
+        let label = (y + 1).to_string();
+        let center_y = mm_to_points(grid_rect.top() - (y - cells.pos.y) as f32 * cell_size - cell_size / 2.0) - text_center_offset;
+        canvas.right_text(mm_to_points(grid_rect.left() - ruler_gap), center_y, ruler_font, render_style.ruler_font_size_pt, &label)?;
+        canvas.left_text(mm_to_points(grid_rect.right() + ruler_gap), center_y, ruler_font, render_style.ruler_font_size_pt, &label)?;
+    }
+
+    Ok(())
+}
+
+fn render_assembly_map_page<D: PatternDocument>(
+    document: &mut D,
+    paper_sheet: &PaperSheet,
+    image_size: Size2U,
+    tile_layout: &TileLayout,
+    render_style: &RenderStyle,
+) -> std::io::Result<()> {
+    let map_area_rect = get_tile_pattern_area_rect(paper_sheet, render_style);
+    let cell_size = mm::new(
+        (map_area_rect.size.w.raw_value() / image_size.w as f32)
+            .min(map_area_rect.size.h.raw_value() / image_size.h as f32)
+    );
+    let map_size = Size2D {
+        w: image_size.w as f32 * cell_size,
+        h: image_size.h as f32 * cell_size,
+    };
+    let map_rect = map_area_rect.get_centered(&map_size);
+
+    let title = format!("Assembly map: {} rows x {} columns, {} pages, {} cells overlap",
+        tile_layout.rows,
+        tile_layout.columns,
+        tile_layout.pages_count(),
+        tile_layout.overlap_cells
+    );
+
+    render_styled_page(
+        document,
+        paper_sheet,
+        render_style,
+        |canvas| {
+            draw_page_header(canvas, paper_sheet, render_style, &title)?;
+
+            tile_layout.tiles.iter()
+                .try_for_each(|tile| {
+                    let core_cells = tile.get_core_cells(tile_layout.overlap_cells);
+                    let tile_rect = Rect2D {
                         pos: Pos2D {
-                            x: image_occupied_area_rect.pos.x + x as f32 * diamond_shape.get_size(),
-                            y: image_occupied_area_rect.pos.y + (flip_y - y - 1) as f32 * diamond_shape.get_size(),
+                            x: map_rect.left() + core_cells.pos.x as f32 * cell_size,
+                            y: map_rect.top() - core_cells.bottom() as f32 * cell_size,
                         },
-                        size: Size2D::new_square(diamond_shape.get_size())
+                        size: Size2D {
+                            w: core_cells.size.w as f32 * cell_size,
+                            h: core_cells.size.h as f32 * cell_size,
+                        }
                     };
-                    
-                    // Pixel's background
-                    draw_filled_rect(
+
+                    draw_empty_bordered_rect(
                         canvas, 
-                        &pixel_rect, 
-                        Color::rgb(pixel.0[0], pixel.0[1], pixel.0[2])
+                        &tile_rect, 
+                        render_style.map_line_width_pt,
+                        Rgb([0, 0, 0])
                     )?;
 
-                    // Symbol
-                    let symbol = dmc_image_legend.get(&ColorRGB::from(*pixel))
-                        .map(|ldmc| ldmc.symbol.to_string())
-                        .unwrap_or(String::from('!'));
-
-                    // Draw contrasting color
-                    canvas.set_fill_color(get_contrasting_color(pixel))?;
+                    let (x, y, _, h) = rect_to_points(&tile_rect);
+                    let center_x = x + mm_to_points(tile_rect.size.w) / 2.0;
+                    canvas.set_fill_color(Rgb([0, 0, 0]))?;
                     canvas.center_text(
-                        mm_to_points(pixel_rect.pos.x) + symbol_x_oiffset, 
-                        mm_to_points(pixel_rect.pos.y) + symbol_y_oiffset, 
-                        font, 
-                        symbol_font_size, 
-                        &symbol
+                        center_x,
+                        y + h / 2.0,
+                        render_style.text_bold_font.into(),
+                        render_style.map_page_number_font_size_pt,
+                        &tile.page_number.to_string()
                     )?;
-
-                    Ok(())
+                    canvas.center_text(
+                        center_x,
+                        y + h / 2.0 - render_style.map_page_number_font_size_pt,
+                        render_style.text_font.into(),
+                        render_style.map_tile_label_font_size_pt,
+                        &format!("{}{}", get_row_label(tile.row), tile.column + 1)
+                    )
                 })
-        })?;
+        })
+}
+
+fn render_legend_pages<D: PatternDocument>(
+    document: &mut D,
+    paper_sheet: &PaperSheet,
+    dmc_image_legend: &ImageDmcLegend,
+    drill_bags_config: &DrillBagsConfig,
+    render_style: &RenderStyle,
+) -> std::io::Result<()> {
+    let row_height_mm = render_style.legend_row_height_mm;
+    let swatch_inset_mm = render_style.legend_swatch_inset_mm;
+    let text_font_size = render_style.legend_text_font_size_pt;
+
+    let records = dmc_image_legend.get_sorted_records();
+    let table_area_rect = get_tile_pattern_area_rect(paper_sheet, render_style);
+    // One row for column titles, one for totals
+    let rows_per_page = ((table_area_rect.size.h.raw_value() / row_height_mm).floor() as usize)
+        .saturating_sub(2)
+        .max(1);
+    let pages_count = records.len().div_ceil(rows_per_page).max(1);
+
+    let total_drills = dmc_image_legend.get_total_count();
+    let total_bags: usize = records.iter()
+        .map(|record| drill_bags_config.get_bags_count(record.count))
+        .sum();
+
+    (0..pages_count).try_for_each(|page_idx| {
+        let page_records = records.iter()
+            .skip(page_idx * rows_per_page)
+            .take(rows_per_page)
+            .collect::<Vec<_>>();
+        let is_last_page = page_idx + 1 == pages_count;
+
+        let title = format!("Legend and shopping list ({}/{}), bag size {}, spare {}%",
+            page_idx + 1,
+            pages_count,
+            drill_bags_config.bag_size,
+            drill_bags_config.spare_percentage
+        );
+
+        render_styled_page(
+            document,
+            paper_sheet,
+            render_style,
+            |canvas| {
+                draw_page_header(canvas, paper_sheet, render_style, &title)?;
+
+                let left = table_area_rect.left();
+                let row_baseline = |row_idx: usize| mm_to_points(
+                    table_area_rect.top() - (row_idx as f32 + 1.0) * mm::new(row_height_mm) + mm::new(row_height_mm / 3.0)
+                );
+                let column_x = |offset_mm: f32| mm_to_points(left + mm::new(offset_mm));
+
+                let draw_text_row = |canvas: &mut dyn PatternCanvas, row_idx: usize, font: PatternFont, columns: [&str; 5]| -> std::io::Result<()> {
+                    let y = row_baseline(row_idx);
+                    canvas.set_fill_color(Rgb([0, 0, 0]))?;
+                    canvas.left_text(column_x(render_style.legend_symbol_column_mm), y, font, text_font_size, columns[0])?;
+                    canvas.left_text(column_x(render_style.legend_code_column_mm), y, font, text_font_size, columns[1])?;
+                    canvas.left_text(column_x(render_style.legend_name_column_mm), y, font, text_font_size, columns[2])?;
+                    canvas.right_text(column_x(render_style.legend_drills_column_end_mm), y, font, text_font_size, columns[3])?;
+                    canvas.right_text(column_x(render_style.legend_bags_column_end_mm), y, font, text_font_size, columns[4])
+                };
+
+                draw_text_row(canvas, 0, render_style.text_bold_font.into(), ["Symbol", "DMC", "Name", "Drills", "Bags"])?;
+
+                page_records.iter()
+                    .enumerate()
+                    .try_for_each(|(idx, record)| {
+                        let row_idx = idx + 1;
+                        let color = record.dmc.color;
+                        let swatch_rect = Rect2D {
+                            pos: Pos2D {
+                                x: left,
+                                y: table_area_rect.top() - (row_idx as f32 + 1.0) * mm::new(row_height_mm) + mm::new(swatch_inset_mm),
+                            },
+                            size: Size2D {
+                                w: mm::new(render_style.legend_swatch_width_mm),
+                                h: mm::new(row_height_mm - 2.0 * swatch_inset_mm)
+                            }
+                        };
+                        draw_filled_rect(canvas, &swatch_rect, Rgb(color.0))?;
+                        draw_empty_bordered_rect(canvas, &swatch_rect, render_style.frame_line_width_pt / 2.0, Rgb([0, 0, 0]))?;
+
+                        canvas.set_fill_color(Rgb([0, 0, 0]))?;
+                        canvas.left_text(column_x(render_style.legend_symbol_column_mm), row_baseline(row_idx), get_symbol_font(&record.symbol, render_style.symbol_font.into()), text_font_size, &record.symbol)?;
+                        draw_text_row(canvas, row_idx, render_style.text_font.into(), [
+                            "",
+                            &record.dmc.code,
+                            &record.dmc.name,
+                            &record.count.to_string(),
+                            &drill_bags_config.get_bags_count(record.count).to_string()
+                        ])
+                    })?;
 
-    // Write all pending content, including the trailer and index
-    document.finish()
+                if is_last_page {
+                    draw_text_row(canvas, page_records.len() + 1, render_style.text_bold_font.into(), [
+                        "",
+                        "",
+                        &format!("Total: {} colors", records.len()),
+                        &total_drills.to_string(),
+                        &total_bags.to_string()
+                    ])?;
+                }
+
+                Ok(())
+            })
+    })
+}
+
+/// Pattern file format, multi page SVG and PNG outputs are split into numbered files
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum OutputFormat {
+    Pdf,
+    Svg,
+    Png {
+        dpi: f32
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternOutput {
+    #[serde(flatten)]
+    pub format: OutputFormat,
+    pub path: String,
+}
+
+impl PatternOutput {
+    pub fn pdf(path: &str) -> Self {
+        Self { format: OutputFormat::Pdf, path: path.to_string() }
+    }
+}
+
+/// Page setup and look of pattern, same for every output
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub paper_sheet: PaperSheet,
+    pub diamond_shape: DiamondShape,
+    pub page_layout: PageLayout,
+    pub drill_bags_config: DrillBagsConfig,
+    pub pattern_pages: PatternPages,
+    pub render_style: RenderStyle,
+    pub draw_grid_lines: bool,
+}
+
+/// Everything needed to render project pages, shared by all outputs
+struct PatternProject<'a> {
+    options: &'a RenderOptions,
+    dmc_image_legend: &'a ImageDmcLegend,
+    dithered_img: &'a RgbImage,
+    cells_mask: &'a CellsMask,
+}
+
+impl PatternProject<'_> {
+    /// Draws image cells bounded by `cells`, starting from bottom left corner of `area_rect`
+    fn draw_diamonds(
+        &self,
+        canvas: &mut dyn PatternCanvas,
+        cells: &Rect2U,
+        area_rect: &Rect2D,
+        page_style: PatternPageStyle,
+    ) -> std::io::Result<()> {
+        let diamond_shape = &self.options.diamond_shape;
+        let render_style = &self.options.render_style;
+        let symbol_font_size = mm_to_points(diamond_shape.get_size()) * render_style.symbol_scale;
+        let symbol_x_oiffset = mm_to_points(diamond_shape.get_size()) / 2.0;
+        let symbol_y_oiffset = mm_to_points(diamond_shape.get_size()) * render_style.symbol_baseline_offset;
+
+        for y in cells.pos.y..cells.bottom() {
+            for x in cells.pos.x..cells.right() {
+                if self.cells_mask.is_empty_cell(x, y) {
+                    continue;
+                }
+
+                let pixel = self.dithered_img.get_pixel(x, y);
+                let pixel_rect = Rect2D {
+                    pos: Pos2D {
+                        x: area_rect.pos.x + (x - cells.pos.x) as f32 * diamond_shape.get_size(),
+                        y: area_rect.pos.y + (cells.bottom() - y - 1) as f32 * diamond_shape.get_size(),
+                    },
+                    size: Size2D::new_square(diamond_shape.get_size())
+                };
+
+                // Symbol
+                let symbol = self.dmc_image_legend.get(&ColorRGB::from(*pixel))
+                    .map(|ldmc| ldmc.symbol.to_string())
+                    .unwrap_or(String::from('!'));
+
+                let symbol_colors = match page_style {
+                    PatternPageStyle::Color => {
+                        // Drill in pixel's color
+                        draw_drill(
+                            canvas, 
+                            diamond_shape, 
+                            &pixel_rect, 
+                            *pixel
+                        )?;
+
+                        get_symbol_colors(&ColorRGB::from(*pixel), render_style)
+                    },
+                    PatternPageStyle::Symbols => (Rgb([0, 0, 0]), None),
+                };
+
+                draw_symbol(
+                    canvas, 
+                    mm_to_points(pixel_rect.pos.x) + symbol_x_oiffset, 
+                    mm_to_points(pixel_rect.pos.y) + symbol_y_oiffset, 
+                    get_symbol_font(&symbol, render_style.symbol_font.into()), 
+                    symbol_font_size, 
+                    &symbol, 
+                    symbol_colors
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_single_page<D: PatternDocument>(
+        &self,
+        document: &mut D,
+        page_style: PatternPageStyle,
+    ) -> std::io::Result<()> {
+        let RenderOptions { paper_sheet, diamond_shape, render_style, .. } = self.options;
+        let printing_area_rect = paper_sheet.get_printing_area_rect();
+        let img_size = Size2D {
+            w: self.dithered_img.width() as f32 * diamond_shape.get_size(),
+            h: self.dithered_img.height() as f32 * diamond_shape.get_size(),
+        };
+        let image_occupied_area_rect = printing_area_rect.get_centered(&img_size);
+        let cells = Rect2U {
+            pos: Pos2U { x: 0, y: 0 },
+            size: Size2U { w: self.dithered_img.width(), h: self.dithered_img.height() }
+        };
+
+        render_styled_page(
+            document,
+            paper_sheet,
+            render_style,
+            |canvas| {
+                draw_template_frames(canvas, render_style, &printing_area_rect, &image_occupied_area_rect)?;
+
+                self.draw_diamonds(canvas, &cells, &image_occupied_area_rect, page_style)?;
+
+                if self.options.draw_grid_lines || page_style == PatternPageStyle::Symbols {
+                    draw_grid(canvas, render_style, diamond_shape, &cells, &image_occupied_area_rect)?;
+                }
+
+                Ok(())
+            })
+    }
+
+    fn render_tile_page<D: PatternDocument>(
+        &self,
+        document: &mut D,
+        tile_layout: &TileLayout,
+        tile: &Tile,
+        page_style: PatternPageStyle,
+    ) -> std::io::Result<()> {
+        let RenderOptions { paper_sheet, diamond_shape, render_style, .. } = self.options;
+        let pattern_area_rect = get_tile_pattern_area_rect(paper_sheet, render_style);
+        let tile_size = Size2D {
+            w: tile.cells.size.w as f32 * diamond_shape.get_size(),
+            h: tile.cells.size.h as f32 * diamond_shape.get_size(),
+        };
+        let tile_occupied_area_rect = pattern_area_rect.get_centered(&tile_size);
+        let core_cells = tile.get_core_cells(tile_layout.overlap_cells);
+
+        render_styled_page(
+            document,
+            paper_sheet,
+            render_style,
+            |canvas| {
+                draw_template_frames(canvas, render_style, &paper_sheet.get_printing_area_rect(), &tile_occupied_area_rect)?;
+
+                let title = match page_style {
+                    PatternPageStyle::Color => tile.get_title(tile_layout.pages_count()),
+                    PatternPageStyle::Symbols => format!("{} (symbols)", tile.get_title(tile_layout.pages_count())),
+                };
+                draw_page_header(canvas, paper_sheet, render_style, &title)?;
+
+                self.draw_diamonds(canvas, &tile.cells, &tile_occupied_area_rect, page_style)?;
+
+                if self.options.draw_grid_lines || page_style == PatternPageStyle::Symbols {
+                    draw_grid(canvas, render_style, diamond_shape, &tile.cells, &tile_occupied_area_rect)?;
+                }
+
+                // Mark cells shared with left and top neighbours
+                if core_cells.pos.x > tile.cells.pos.x {
+                    let x = tile_occupied_area_rect.left() + (core_cells.pos.x - tile.cells.pos.x) as f32 * diamond_shape.get_size();
+                    draw_line(
+                        canvas,
+                        Pos2D { x, y: tile_occupied_area_rect.bottom() },
+                        Pos2D { x, y: tile_occupied_area_rect.top() },
+                        render_style.overlap_line_width_pt,
+                        Rgb(render_style.overlap_line_color)
+                    )?;
+                }
+
+                if core_cells.pos.y > tile.cells.pos.y {
+                    let y = tile_occupied_area_rect.top() - (core_cells.pos.y - tile.cells.pos.y) as f32 * diamond_shape.get_size();
+                    draw_line(
+                        canvas,
+                        Pos2D { x: tile_occupied_area_rect.left(), y },
+                        Pos2D { x: tile_occupied_area_rect.right(), y },
+                        render_style.overlap_line_width_pt,
+                        Rgb(render_style.overlap_line_color)
+                    )?;
+                }
+
+                Ok(())
+            })
+    }
+
+    fn render_to<D: PatternDocument>(&self, mut document: D) -> std::io::Result<()> {
+        let RenderOptions { paper_sheet, diamond_shape, render_style, .. } = self.options;
+
+        match self.options.page_layout {
+            PageLayout::SinglePage => {
+                self.options.pattern_pages.get_styles().iter()
+                    .try_for_each(|page_style| self.render_single_page(&mut document, *page_style))?;
+            },
+            PageLayout::Tiled { overlap_cells } => {
+                let image_size = Size2U { w: self.dithered_img.width(), h: self.dithered_img.height() };
+                let tile_layout = TileLayout::for_paper_sheet(
+                    paper_sheet, 
+                    diamond_shape, 
+                    render_style, 
+                    image_size, 
+                    overlap_cells
+                );
+
+                render_assembly_map_page(&mut document, paper_sheet, image_size, &tile_layout, render_style)?;
+
+                self.options.pattern_pages.get_styles().iter()
+                    .flat_map(|page_style| tile_layout.tiles.iter().map(move |tile| (*page_style, tile)))
+                    .try_for_each(|(page_style, tile)| self.render_tile_page(&mut document, &tile_layout, tile, page_style))?;
+            },
+        }
+
+        render_legend_pages(&mut document, paper_sheet, self.dmc_image_legend, &self.options.drill_bags_config, render_style)?;
+
+        // Write all pending content, including the trailer and index
+        document.finish()
+    }
+}
+
+/// Grid rulers are drawn around printing area, reserve space using `reserve_grid_rulers_area`.
+/// Every output gets the same pages, empty cells of `cells_mask` are left blank
+pub fn render_diamond_painting_project(
+    options: &RenderOptions,
+    dmc_image_legend: &ImageDmcLegend,
+    dithered_img: &RgbImage,
+    cells_mask: &CellsMask,
+    outputs: &[PatternOutput],
+) -> std::io::Result<()> {
+    let project = PatternProject {
+        options,
+        dmc_image_legend,
+        dithered_img,
+        cells_mask,
+    };
+
+    outputs.iter().try_for_each(|output| match output.format {
+        OutputFormat::Pdf => project.render_to(PdfDocument::create(&output.path)?),
+        OutputFormat::Svg => project.render_to(SvgDocument::create(&output.path)),
+        OutputFormat::Png { dpi } => project.render_to(PngDocument::create(&output.path, dpi)?),
+    })
 }
 
-fn get_contrasting_color(pixel: &Rgb<u8>) -> Color {
-    let channel_color = pixel.0[0] as u32 + pixel.0[1] as u32 + pixel.0[2] as u32;
-    let channel_color = if channel_color > 300 { 0 } else { 255 };
-    Color::rgb(channel_color, channel_color, channel_color)
-}
\ No newline at end of file
+#[cfg(test)]
+mod test_render {
+    use ditherum::color::ColorRGB;
+    use image::{
+        GrayImage,
+        Luma,
+        Rgb,
+        RgbImage
+    };
+
+    use crate::{
+        dmc::{
+            get_colors_counts,
+            DrillBagsConfig,
+            ImageDmcLegend,
+            PaletteDmc
+        },
+        mask::CellsMask,
+        style::RenderStyle,
+        types::{
+            DiamondShape,
+            PaperSheet
+        }
+    };
+
+    use super::{
+        get_symbol_colors,
+        render_diamond_painting_project,
+        OutputFormat,
+        PageLayout,
+        PatternOutput,
+        PatternPages,
+        RenderOptions
+    };
+
+    fn outlined_style() -> RenderStyle {
+        RenderStyle {
+            symbol_outline_min_contrast: Some(7.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_symbol_colors_dark_cell() {
+        let (symbol_color, outline_color) = get_symbol_colors(&ColorRGB([20, 20, 30]), &outlined_style());
+        assert_eq!(symbol_color, Rgb([255, 255, 255]));
+        assert_eq!(outline_color, None);
+    }
+
+    #[test]
+    fn test_symbol_colors_light_cell() {
+        let (symbol_color, outline_color) = get_symbol_colors(&ColorRGB([245, 245, 240]), &outlined_style());
+        assert_eq!(symbol_color, Rgb([0, 0, 0]));
+        assert_eq!(outline_color, None);
+    }
+
+    #[test]
+    fn test_symbol_colors_mid_grey_cell_outlined() {
+        // Neither black nor white reaches 7:1 on mid grey
+        let (symbol_color, outline_color) = get_symbol_colors(&ColorRGB([119, 119, 119]), &outlined_style());
+        assert_eq!(symbol_color, Rgb([0, 0, 0]));
+        assert_eq!(outline_color, Some(Rgb([255, 255, 255])));
+
+        let (_, outline_color) = get_symbol_colors(&ColorRGB([119, 119, 119]), &RenderStyle::default());
+        assert_eq!(outline_color, None);
+    }
+
+    /// Black and blue halves of 12x12 cells, pages of SVG output in order
+    fn render_svg_pages(diamond_shape: DiamondShape, pattern_pages: PatternPages, render_style: RenderStyle, name: &str) -> Vec<String> {
+        let palette = PaletteDmc::load_dmc_palette().unwrap();
+        let black = palette.find_code_dmc("310").unwrap();
+        let blue = palette.find_code_dmc("797").unwrap();
+        let dithered_img = RgbImage::from_fn(12, 12, |x, _| Rgb(if x < 6 { black.color.0 } else { blue.color.0 }));
+        let cells_mask = CellsMask::from_coverage(&GrayImage::from_pixel(12, 12, Luma([255])));
+        let palette = PaletteDmc(vec![black, blue]);
+        let colors_counts = get_colors_counts(&dithered_img, &cells_mask);
+        let dmc_image_legend = ImageDmcLegend::extract_from(&palette, &colors_counts, &["A", "★"]).unwrap();
+
+        let output_path = std::env::temp_dir().join(format!("diamonds_imager_generator_{name}.svg"));
+        let options = RenderOptions {
+            paper_sheet: PaperSheet::standard_a4(),
+            diamond_shape,
+            page_layout: PageLayout::SinglePage,
+            drill_bags_config: DrillBagsConfig::default(),
+            pattern_pages,
+            render_style,
+            draw_grid_lines: false,
+        };
+        render_diamond_painting_project(
+            &options,
+            &dmc_image_legend,
+            &dithered_img,
+            &cells_mask,
+            &[PatternOutput { format: OutputFormat::Svg, path: output_path.to_string_lossy().to_string() }]
+        ).unwrap();
+
+        (1..)
+            .map(|page_number| std::env::temp_dir().join(format!("diamonds_imager_generator_{name}_{page_number}.svg")))
+            .map_while(|page_path| {
+                let page = std::fs::read_to_string(&page_path).ok()?;
+                std::fs::remove_file(page_path).ok()?;
+                Some(page)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_and_square_drills() {
+        let pages = render_svg_pages(DiamondShape::common_round(), PatternPages::Color, RenderStyle::default(), "round_drills");
+        // Color page and legend
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].matches("<circle").count(), 144);
+
+        let pages = render_svg_pages(DiamondShape::common_square(), PatternPages::Color, RenderStyle::default(), "square_drills");
+        assert_eq!(pages[0].matches("<circle").count(), 0);
+        assert_eq!(pages[0].matches(r##"fill="#000000"/>"##).count(), 72);
+    }
+
+    #[test]
+    fn test_symbols_pages_grid_and_rulers() {
+        let pages = render_svg_pages(DiamondShape::common_square(), PatternPages::ColorAndSymbols, RenderStyle::default(), "symbols_pages");
+        assert_eq!(pages.len(), 3);
+        let (color_page, symbols_page, legend_page) = (&pages[0], &pages[1], &pages[2]);
+
+        // Color page has no grid, white symbols on black drills
+        assert!(!color_page.contains("<line"));
+        assert_eq!(color_page.matches(r##"fill="#ffffff">A</text>"##).count(), 72);
+
+        // 13 lines each way, cell lines thin, every 10th one bold
+        assert_eq!(symbols_page.matches("<line").count(), 26);
+        assert_eq!(symbols_page.matches(r#"stroke-width="0.90"/>"#).count(), 4);
+        assert_eq!(symbols_page.matches(r#"stroke-width="0.20"/>"#).count(), 22);
+        // Every cell labeled on all four sides
+        for label in 1..=12 {
+            assert_eq!(symbols_page.matches(&format!(">{label}</text>")).count(), 4, "{label}");
+        }
+        for anchor in ["start", "middle", "end"] {
+            assert!(symbols_page.contains(&format!(r##"text-anchor="{anchor}" fill="#000000">10</text>"##)));
+        }
+
+        // Black symbols only, no drills
+        assert_eq!(symbols_page.matches(r##"fill="#000000">A</text>"##).count(), 72);
+        assert_eq!(symbols_page.matches(r##"fill="#000000">★</text>"##).count(), 72);
+        assert!(!symbols_page.contains(r##"fill="#ffffff">"##));
+
+        assert!(legend_page.contains(">★</text>"));
+        assert!(legend_page.contains("310"));
+    }
+
+    #[test]
+    fn test_ruler_label_interval() {
+        let render_style = RenderStyle {
+            ruler_label_interval: 10,
+            ..Default::default()
+        };
+        let pages = render_svg_pages(DiamondShape::common_square(), PatternPages::Symbols, render_style, "ruler_interval");
+        let symbols_page = &pages[0];
+
+        // Cells 1 and 10 only
+        assert_eq!(symbols_page.matches(">1</text>").count(), 4);
+        assert_eq!(symbols_page.matches(">10</text>").count(), 4);
+        assert!(!symbols_page.contains(">2</text>"));
+        assert!(!symbols_page.contains(">11</text>"));
+    }
+}
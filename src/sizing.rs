@@ -0,0 +1,547 @@
+use image::{
+    imageops::{
+        self,
+        FilterType
+    },
+    GrayImage,
+    ImageBuffer,
+    Luma,
+    Pixel,
+    Rgb,
+    Rgb32FImage,
+    RgbImage
+};
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::types::{
+    serde_size_mm,
+    DiamondShape,
+    Pos2U,
+    Rect2U,
+    Size2D,
+    Size2F,
+    Size2U
+};
+
+/// How pattern size is chosen
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingPolicy {
+    /// Image fits printable area of single page
+    #[default]
+    FitToPaper,
+    /// Exact canvas size, rounded to whole drills
+    CanvasMillimeters(#[serde(with = "serde_size_mm")] Size2D),
+    /// Exact canvas size in drills
+    CanvasDrills(Size2U),
+    /// Largest canvas of image aspect ratio with at most that many drills
+    DrillBudget(u32),
+}
+
+/// How image is matched to canvas aspect ratio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectFit {
+    /// `Fit` for fit to paper, `Fill` for exact canvas
+    #[default]
+    Auto,
+    /// Whole image, canvas gets smaller along one axis
+    Fit,
+    /// Fills canvas, image edges away from focal point are cut off
+    Fill,
+    /// Whole image centered on canvas, rest filled with border color
+    Letterbox,
+}
+
+/// Filter used to resize image to canvas cells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResamplingFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+    /// Mean of all image pixels under each cell, no aliasing even on large reductions
+    #[default]
+    AreaAverage,
+}
+
+/// Canvas bigger than single page is split across many pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanvasSizing {
+    pub policy: SizingPolicy,
+    pub aspect_fit: AspectFit,
+    pub resampling_filter: ResamplingFilter,
+    /// Part of image used for pattern in image pixels, whole image if None
+    pub crop_region: Option<Rect2U>,
+    /// Point kept in view by `Fill`, relative to cropped image, center if None
+    pub focal_point: Option<(f32, f32)>,
+    /// Letterbox border DMC code from provided palette, white border if None
+    pub border_dmc_code: Option<String>,
+    pub overlap_cells: u32,
+}
+
+impl Default for CanvasSizing {
+    fn default() -> Self {
+        Self {
+            policy: SizingPolicy::default(),
+            aspect_fit: AspectFit::default(),
+            resampling_filter: ResamplingFilter::default(),
+            crop_region: None,
+            focal_point: None,
+            border_dmc_code: None,
+            overlap_cells: 2
+        }
+    }
+}
+
+/// Image resized to canvas cells
+#[derive(Debug, Clone)]
+pub struct FittedImage {
+    pub img: RgbImage,
+    /// Mask coverage of cells, 255 for fully kept cell
+    pub mask: Option<GrayImage>,
+    /// Cells showing the image, rest is letterbox border
+    pub content_cells: Rect2U,
+}
+
+impl SizingPolicy {
+    /// None for `FitToPaper`, size depends on paper sheet then
+    pub fn get_canvas_size_in_diamonds(&self, diamond_shape: &DiamondShape, image_size: Size2U) -> Option<Size2U> {
+        match self {
+            SizingPolicy::FitToPaper => None,
+            SizingPolicy::CanvasMillimeters(size) => Some(Size2U {
+                w: ((size.w / diamond_shape.get_size()).round() as u32).max(1),
+                h: ((size.h / diamond_shape.get_size()).round() as u32).max(1),
+            }),
+            SizingPolicy::CanvasDrills(size) => Some(Size2U {
+                w: size.w.max(1),
+                h: size.h.max(1),
+            }),
+            SizingPolicy::DrillBudget(drills_count) => {
+                let aspect_ratio = Size2F::from(&image_size).get_aspect_ratio();
+                let h = (*drills_count as f32 / aspect_ratio).sqrt();
+                Some(Size2U {
+                    w: ((h * aspect_ratio).floor() as u32).max(1),
+                    h: (h.floor() as u32).max(1),
+                })
+            },
+        }
+    }
+}
+
+impl AspectFit {
+    pub fn resolve(&self, policy: &SizingPolicy) -> Self {
+        match (self, policy) {
+            (AspectFit::Auto, SizingPolicy::FitToPaper) => AspectFit::Fit,
+            (AspectFit::Auto, _) => AspectFit::Fill,
+            (aspect_fit, _) => *aspect_fit,
+        }
+    }
+}
+
+type Gray32FImage = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+/// Channel types resized in f32
+trait ResampledChannel: Copy + Into<f32> {
+    fn from_f32(value: f32) -> Self;
+}
+
+impl ResampledChannel for u8 {
+    fn from_f32(value: f32) -> Self {
+        value.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+impl ResampledChannel for f32 {
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+}
+
+impl ResamplingFilter {
+    pub fn resize<P>(&self, img: &ImageBuffer<P, Vec<u8>>, size: Size2U) -> ImageBuffer<P, Vec<u8>>
+    where
+        P: Pixel<Subpixel = u8> + 'static
+    {
+        self.resize_any(img, size)
+    }
+
+    fn resize_any<P>(&self, img: &ImageBuffer<P, Vec<P::Subpixel>>, size: Size2U) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: Pixel + 'static,
+        P::Subpixel: ResampledChannel + 'static
+    {
+        let filter_type = match self {
+            ResamplingFilter::Nearest => FilterType::Nearest,
+            ResamplingFilter::Triangle => FilterType::Triangle,
+            ResamplingFilter::CatmullRom => FilterType::CatmullRom,
+            ResamplingFilter::Lanczos3 => FilterType::Lanczos3,
+            ResamplingFilter::AreaAverage => return resize_area_average(img, size),
+        };
+        imageops::resize(img, size.w, size.h, filter_type)
+    }
+}
+
+/// Source pixels covered by each destination pixel along one axis, with weights summing to 1
+fn get_area_weights(src_length: u32, dst_length: u32) -> Vec<Vec<(usize, f32)>> {
+    let scale = src_length as f64 / dst_length as f64;
+    (0..dst_length)
+        .map(|dst| {
+            let start = dst as f64 * scale;
+            let end = (dst + 1) as f64 * scale;
+            (start.floor() as u32..(end.ceil() as u32).min(src_length))
+                .map(|src| (src as usize, ((end.min(src as f64 + 1.0) - start.max(src as f64)) / scale) as f32))
+                .filter(|(_, weight)| *weight > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+/// Separable box filter with fractional coverage of edge pixels
+fn resize_area_average<P>(img: &ImageBuffer<P, Vec<P::Subpixel>>, size: Size2U) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static,
+    P::Subpixel: ResampledChannel
+{
+    let channels = P::CHANNEL_COUNT as usize;
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let dst_width = size.w as usize;
+    let src = img.as_raw();
+
+    // Rows resized horizontally first
+    let mut rows = vec![0.0f32; dst_width * height * channels];
+    for (dx, weights) in get_area_weights(img.width(), size.w).iter().enumerate() {
+        for y in 0..height {
+            let dst_px = &mut rows[(y * dst_width + dx) * channels..][..channels];
+            for (sx, weight) in weights {
+                let src_px = &src[(y * width + sx) * channels..][..channels];
+                dst_px.iter_mut()
+                    .zip(src_px)
+                    .for_each(|(dst_channel, src_channel)| *dst_channel += (*src_channel).into() * weight);
+            }
+        }
+    }
+
+    let column_weights = get_area_weights(img.height(), size.h);
+    ImageBuffer::from_fn(size.w, size.h, |dx, dy| {
+        let mut px = [P::Subpixel::from_f32(0.0); 4];
+        for (channel_idx, channel) in px[..channels].iter_mut().enumerate() {
+            let value = column_weights[dy as usize].iter()
+                .map(|(sy, weight)| rows[(sy * dst_width + dx as usize) * channels + channel_idx] * weight)
+                .sum::<f32>();
+            *channel = P::Subpixel::from_f32(value);
+        }
+        *P::from_slice(&px[..channels])
+    })
+}
+
+/// Largest size of image aspect ratio inside `canvas_size`
+fn get_fit_size(image_size: Size2U, canvas_size: Size2U) -> Size2U {
+    let image_aspect_ratio = Size2F::from(&image_size).get_aspect_ratio();
+    if image_aspect_ratio > Size2F::from(&canvas_size).get_aspect_ratio() {
+        Size2U {
+            w: canvas_size.w,
+            h: ((canvas_size.w as f32 / image_aspect_ratio).round() as u32).clamp(1, canvas_size.h),
+        }
+    } else {
+        Size2U {
+            w: ((canvas_size.h as f32 * image_aspect_ratio).round() as u32).clamp(1, canvas_size.w),
+            h: canvas_size.h,
+        }
+    }
+}
+
+/// Start of `length` long window kept around `focal_point` within `total`
+fn get_window_start(total: u32, length: u32, focal_point: f32) -> u32 {
+    let start = focal_point.clamp(0.0, 1.0) * total as f32 - length as f32 / 2.0;
+    (start.round().max(0.0) as u32).min(total - length)
+}
+
+/// Region clamped to image bounds, whole image if region is empty or outside
+pub fn crop_image_region<P>(img: ImageBuffer<P, Vec<P::Subpixel>>, crop_region: Option<Rect2U>) -> ImageBuffer<P, Vec<P::Subpixel>>
+where
+    P: Pixel + 'static
+{
+    let Some(region) = crop_region else {
+        return img;
+    };
+
+    let (width, height) = img.dimensions();
+    let x = region.pos.x.min(width);
+    let y = region.pos.y.min(height);
+    let w = region.size.w.min(width - x);
+    let h = region.size.h.min(height - y);
+    if w == 0 || h == 0 {
+        return img;
+    }
+    imageops::crop_imm(&img, x, y, w, h).to_image()
+}
+
+/// Colors are weighted by mask, so masked out pixels do not bleed into kept ones
+fn resize_with_mask(
+    rgb_img: RgbImage,
+    mask: Option<GrayImage>,
+    size: Size2U,
+    resampling_filter: ResamplingFilter
+) -> (RgbImage, Option<GrayImage>) {
+    let Some(mask) = mask else {
+        return (resampling_filter.resize(&rgb_img, size), None);
+    };
+
+    // Premultiplied in f32, so dark colors under low coverage are not rounded away
+    let (width, height) = rgb_img.dimensions();
+    let coverage = Gray32FImage::from_fn(width, height, |x, y| Luma([mask.get_pixel(x, y).0[0] as f32 / 255.0]));
+    let premultiplied_img = Rgb32FImage::from_fn(width, height, |x, y| {
+        let alpha = coverage.get_pixel(x, y).0[0];
+        Rgb(rgb_img.get_pixel(x, y).0.map(|channel| channel as f32 * alpha))
+    });
+    let resized_img = resampling_filter.resize_any(&premultiplied_img, size);
+    let resized_coverage = resampling_filter.resize_any(&coverage, size);
+
+    let img = RgbImage::from_fn(size.w, size.h, |x, y| {
+        let alpha = resized_coverage.get_pixel(x, y).0[0];
+        let px = resized_img.get_pixel(x, y).0;
+        if alpha > 0.0 {
+            Rgb(px.map(|channel| u8::from_f32(channel / alpha)))
+        } else {
+            Rgb(px.map(u8::from_f32))
+        }
+    });
+    let mask = GrayImage::from_fn(size.w, size.h, |x, y| Luma([u8::from_f32(resized_coverage.get_pixel(x, y).0[0] * 255.0)]));
+    (img, Some(mask))
+}
+
+/// Resizes image and its mask to `canvas_size` cells, matching aspect ratio by resolved `aspect_fit`
+pub fn fit_image_on_canvas_size(
+    rgb_img: RgbImage,
+    mask: Option<GrayImage>,
+    canvas_size: Size2U,
+    aspect_fit: AspectFit,
+    resampling_filter: ResamplingFilter,
+    focal_point: (f32, f32),
+    border_color: Rgb<u8>
+) -> FittedImage {
+    let (width, height) = rgb_img.dimensions();
+    let image_size = Size2U { w: width, h: height };
+
+    match aspect_fit {
+        AspectFit::Auto | AspectFit::Fit => {
+            let fit_size = get_fit_size(image_size, canvas_size);
+            let (img, mask) = resize_with_mask(rgb_img, mask, fit_size, resampling_filter);
+            FittedImage {
+                img,
+                mask,
+                content_cells: Rect2U { pos: Pos2U { x: 0, y: 0 }, size: fit_size }
+            }
+        },
+        AspectFit::Fill => {
+            // Largest window of canvas aspect ratio, in image pixels
+            let window_size = get_fit_size(canvas_size, image_size);
+            let window = Rect2U {
+                pos: Pos2U {
+                    x: get_window_start(width, window_size.w, focal_point.0),
+                    y: get_window_start(height, window_size.h, focal_point.1),
+                },
+                size: window_size
+            };
+            let (img, mask) = resize_with_mask(
+                crop_image_region(rgb_img, Some(window)),
+                mask.map(|mask| crop_image_region(mask, Some(window))),
+                canvas_size,
+                resampling_filter
+            );
+            FittedImage {
+                img,
+                mask,
+                content_cells: Rect2U { pos: Pos2U { x: 0, y: 0 }, size: canvas_size }
+            }
+        },
+        AspectFit::Letterbox => {
+            let fit_size = get_fit_size(image_size, canvas_size);
+            let content_cells = Rect2U {
+                pos: Pos2U {
+                    x: (canvas_size.w - fit_size.w) / 2,
+                    y: (canvas_size.h - fit_size.h) / 2,
+                },
+                size: fit_size
+            };
+            let (content_img, content_mask) = resize_with_mask(rgb_img, mask, fit_size, resampling_filter);
+
+            let mut canvas_img = RgbImage::from_pixel(canvas_size.w, canvas_size.h, border_color);
+            imageops::replace(&mut canvas_img, &content_img, content_cells.pos.x as i64, content_cells.pos.y as i64);
+            // Border is always covered
+            let canvas_mask = content_mask.map(|content_mask| {
+                let mut canvas_mask = GrayImage::from_pixel(canvas_size.w, canvas_size.h, Luma([255]));
+                imageops::replace(&mut canvas_mask, &content_mask, content_cells.pos.x as i64, content_cells.pos.y as i64);
+                canvas_mask
+            });
+            FittedImage {
+                img: canvas_img,
+                mask: canvas_mask,
+                content_cells
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod test_sizing {
+    use image::{
+        GrayImage,
+        Luma,
+        Rgb,
+        RgbImage
+    };
+    use millimeter::mm;
+
+    use crate::types::{
+        DiamondShape,
+        Pos2U,
+        Rect2U,
+        Size2D,
+        Size2U
+    };
+
+    use super::{
+        crop_image_region,
+        fit_image_on_canvas_size,
+        AspectFit,
+        ResamplingFilter,
+        SizingPolicy
+    };
+
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+    const BLUE: Rgb<u8> = Rgb([0, 0, 200]);
+
+    #[test]
+    fn test_canvas_size_in_diamonds() {
+        let image_size = Size2U { w: 300, h: 200 };
+        let diamond_shape = DiamondShape::common_square();
+
+        assert!(SizingPolicy::FitToPaper.get_canvas_size_in_diamonds(&diamond_shape, image_size).is_none());
+
+        let canvas_mm = SizingPolicy::CanvasMillimeters(Size2D { w: mm::new(400.0), h: mm::new(500.0) });
+        assert_eq!(canvas_mm.get_canvas_size_in_diamonds(&diamond_shape, image_size), Some(Size2U { w: 160, h: 200 }));
+
+        let canvas_drills = SizingPolicy::CanvasDrills(Size2U { w: 80, h: 60 });
+        assert_eq!(canvas_drills.get_canvas_size_in_diamonds(&diamond_shape, image_size), Some(Size2U { w: 80, h: 60 }));
+    }
+
+    #[test]
+    fn test_drill_budget_keeps_aspect_ratio() {
+        let image_size = Size2U { w: 300, h: 200 };
+        let budget = SizingPolicy::DrillBudget(15000)
+            .get_canvas_size_in_diamonds(&DiamondShape::common_round(), image_size)
+            .unwrap();
+
+        assert!(budget.w * budget.h <= 15000);
+        assert!(budget.w * budget.h > 14000);
+        assert!((budget.w as f32 / budget.h as f32 - 1.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_auto_aspect_fit() {
+        assert_eq!(AspectFit::Auto.resolve(&SizingPolicy::FitToPaper), AspectFit::Fit);
+        assert_eq!(AspectFit::Auto.resolve(&SizingPolicy::DrillBudget(100)), AspectFit::Fill);
+        assert_eq!(AspectFit::Letterbox.resolve(&SizingPolicy::FitToPaper), AspectFit::Letterbox);
+    }
+
+    #[test]
+    fn test_fit_fill_and_letterbox() {
+        let img = RgbImage::from_pixel(300, 200, BLUE);
+        let square = Size2U { w: 50, h: 50 };
+
+        let fit = fit_image_on_canvas_size(img.clone(), None, square, AspectFit::Fit, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        assert_eq!(fit.img.dimensions(), (50, 33));
+
+        let fill = fit_image_on_canvas_size(img.clone(), None, square, AspectFit::Fill, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        assert_eq!(fill.img.dimensions(), (50, 50));
+        assert_eq!(fill.content_cells.size, square);
+
+        let letterbox = fit_image_on_canvas_size(img, None, square, AspectFit::Letterbox, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        assert_eq!(letterbox.img.dimensions(), (50, 50));
+        assert_eq!(letterbox.content_cells, Rect2U { pos: Pos2U { x: 0, y: 8 }, size: Size2U { w: 50, h: 33 } });
+        assert_eq!(*letterbox.img.get_pixel(25, 0), WHITE);
+        assert_eq!(*letterbox.img.get_pixel(25, 25), BLUE);
+    }
+
+    #[test]
+    fn test_fill_keeps_focal_point() {
+        // Blue on the left edge only
+        let img = RgbImage::from_fn(300, 100, |x, _| if x < 50 { BLUE } else { WHITE });
+        let square = Size2U { w: 10, h: 10 };
+
+        let centered = fit_image_on_canvas_size(img.clone(), None, square, AspectFit::Fill, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        assert!(centered.img.pixels().all(|px| *px == WHITE));
+
+        let left = fit_image_on_canvas_size(img, None, square, AspectFit::Fill, ResamplingFilter::AreaAverage, (0.0, 0.5), WHITE);
+        assert_eq!(*left.img.get_pixel(0, 5), BLUE);
+    }
+
+    #[test]
+    fn test_crop_region() {
+        let img = RgbImage::from_fn(100, 100, |x, y| if x >= 20 && y >= 30 { BLUE } else { WHITE });
+        let cropped = crop_image_region(img.clone(), Some(Rect2U {
+            pos: Pos2U { x: 20, y: 30 },
+            size: Size2U { w: 500, h: 40 }
+        }));
+        assert_eq!(cropped.dimensions(), (80, 40));
+        assert!(cropped.pixels().all(|px| *px == BLUE));
+
+        assert_eq!(crop_image_region(img.clone(), None), img);
+    }
+
+    #[test]
+    fn test_area_average_resize() {
+        // One pixel wide stripes alias with point sampling
+        let stripes = RgbImage::from_fn(100, 10, |x, _| if x % 2 == 0 { Rgb([0, 0, 0]) } else { Rgb([255, 255, 255]) });
+        let size = Size2U { w: 10, h: 1 };
+
+        let nearest = ResamplingFilter::Nearest.resize(&stripes, size);
+        assert!(nearest.pixels().all(|px| px.0[0] == 0 || px.0[0] == 255));
+        let area_average = ResamplingFilter::AreaAverage.resize(&stripes, size);
+        assert!(area_average.pixels().all(|px| px.0[0] == 128));
+
+        // Edge pixels shared by neighbouring cells count partially
+        let ramp = GrayImage::from_fn(3, 1, |x, _| Luma([[0, 90, 180][x as usize]]));
+        let resized = ResamplingFilter::AreaAverage.resize(&ramp, Size2U { w: 2, h: 1 });
+        assert_eq!(resized.as_raw(), &vec![30, 150]);
+    }
+
+    #[test]
+    fn test_fit_with_mask() {
+        // Left half masked out and black, right half blue
+        let img = RgbImage::from_fn(30, 10, |x, _| if x < 15 { Rgb([0, 0, 0]) } else { BLUE });
+        let mask = GrayImage::from_fn(30, 10, |x, _| if x < 15 { Luma([0]) } else { Luma([255]) });
+
+        let fit = fit_image_on_canvas_size(img.clone(), Some(mask.clone()), Size2U { w: 3, h: 1 }, AspectFit::Fit, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        let fit_mask = fit.mask.unwrap();
+        assert!(fit_mask.get_pixel(0, 0).0[0] < 64);
+        assert!(fit_mask.get_pixel(2, 0).0[0] > 192);
+        // Partially masked cell keeps color of kept pixels only
+        assert!(fit_mask.get_pixel(1, 0).0[0] > 0);
+        assert!(fit.img.get_pixel(1, 0).0[2] > 180);
+
+        let letterbox = fit_image_on_canvas_size(img, Some(mask), Size2U { w: 6, h: 6 }, AspectFit::Letterbox, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        let letterbox_mask = letterbox.mask.unwrap();
+        assert_eq!(letterbox_mask.dimensions(), (6, 6));
+        assert_eq!(letterbox_mask.get_pixel(0, 0).0, [255]);
+        assert!(letterbox_mask.get_pixel(0, 3).0[0] < 64);
+    }
+
+    #[test]
+    fn test_fit_with_low_mask_coverage_keeps_color() {
+        let color = Rgb([3, 100, 250]);
+        let img = RgbImage::from_pixel(20, 20, color);
+        let mask = GrayImage::from_pixel(20, 20, Luma([40]));
+
+        let fit = fit_image_on_canvas_size(img, Some(mask), Size2U { w: 10, h: 10 }, AspectFit::Fit, ResamplingFilter::AreaAverage, (0.5, 0.5), WHITE);
+        assert!(fit.img.pixels().all(|px| *px == color));
+        assert!(fit.mask.unwrap().pixels().all(|px| px.0 == [40]));
+    }
+}
@@ -0,0 +1,271 @@
+use std::path::Path;
+
+use millimeter::mm;
+use serde::{
+    Deserialize,
+    Serialize
+};
+
+use crate::{
+    backend::PatternFont,
+    config_file::load_toml_or_json
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderStyleError {
+    #[error("IoError, reason={0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("SerdeJsonError, reason={0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+
+    #[error("TomlError, reason={0}")]
+    TomlError(#[from] toml::de::Error),
+}
+
+/// Text fonts, DejaVu faces embedded in every output.
+/// Names of standard PDF fonts replaced by them are still accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleFont {
+    #[serde(alias = "helvetica")]
+    DejaVuSans,
+    #[serde(alias = "helvetica_bold")]
+    DejaVuSansBold,
+    #[serde(alias = "courier")]
+    DejaVuSansMono,
+    #[serde(alias = "courier_bold")]
+    DejaVuSansMonoBold,
+    #[serde(alias = "times_roman")]
+    DejaVuSerif,
+    #[serde(alias = "times_bold")]
+    DejaVuSerifBold,
+}
+
+impl From<StyleFont> for PatternFont {
+    fn from(value: StyleFont) -> Self {
+        match value {
+            StyleFont::DejaVuSans => PatternFont::Sans,
+            StyleFont::DejaVuSansBold => PatternFont::SansBold,
+            StyleFont::DejaVuSansMono => PatternFont::Mono,
+            StyleFont::DejaVuSansMonoBold => PatternFont::MonoBold,
+            StyleFont::DejaVuSerif => PatternFont::Serif,
+            StyleFont::DejaVuSerifBold => PatternFont::SerifBold,
+        }
+    }
+}
+
+/// Look of generated PDF, missing fields in loaded file take default values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderStyle {
+    pub header_font: StyleFont,
+    pub header_font_size_pt: f32,
+    /// Band above the grid, grows to fit `header_font_size_pt`
+    pub page_header_height_mm: f32,
+    pub text_font: StyleFont,
+    pub text_bold_font: StyleFont,
+    /// Used for latin symbols, greek and geometric ones have dedicated fonts
+    pub symbol_font: StyleFont,
+
+    /// Symbol height relative to drill size
+    pub symbol_scale: f32,
+    /// Symbol baseline above cell bottom, relative to drill size
+    pub symbol_baseline_offset: f32,
+    /// Outline symbols with WCAG contrast ratio lower than that, e.g. 7.0 for AAA level
+    pub symbol_outline_min_contrast: Option<f32>,
+
+    pub show_margins_frame: bool,
+    pub show_occupied_area_frame: bool,
+    pub frame_line_width_pt: f32,
+    pub margins_frame_color: [u8; 3],
+    pub occupied_area_frame_color: [u8; 3],
+
+    pub overlap_line_width_pt: f32,
+    pub overlap_line_color: [u8; 3],
+
+    pub grid_cell_line_width_pt: f32,
+    pub grid_block_line_width_pt: f32,
+    pub grid_line_color: [u8; 3],
+
+    pub map_line_width_pt: f32,
+    pub map_page_number_font_size_pt: f32,
+    pub map_tile_label_font_size_pt: f32,
+
+    pub footer_font_size_pt: f32,
+    /// Cell indices around the grid
+    pub ruler_font_size_pt: f32,
+    /// Band around the grid, grows to fit `ruler_font_size_pt`
+    pub grid_ruler_size_mm: f32,
+    /// Between grid edge and cell indices
+    pub ruler_gap_mm: f32,
+    /// Label first cell and every n-th one, 1 labels all cells
+    pub ruler_label_interval: u32,
+
+    pub legend_text_font_size_pt: f32,
+    pub legend_row_height_mm: f32,
+    pub legend_swatch_width_mm: f32,
+    /// Space above and below swatch within its row
+    pub legend_swatch_inset_mm: f32,
+    /// Legend columns left edges, from table left edge
+    pub legend_symbol_column_mm: f32,
+    pub legend_code_column_mm: f32,
+    pub legend_name_column_mm: f32,
+    /// Numeric columns are right aligned to these edges
+    pub legend_drills_column_end_mm: f32,
+    pub legend_bags_column_end_mm: f32,
+
+    /// Shown on the right side of every page header
+    pub header_text: Option<String>,
+    /// Shown in bottom margin of every page
+    pub footer_text: Option<String>,
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        Self {
+            header_font: StyleFont::DejaVuSansBold,
+            header_font_size_pt: 11.0,
+            page_header_height_mm: 10.0,
+            text_font: StyleFont::DejaVuSans,
+            text_bold_font: StyleFont::DejaVuSansBold,
+            symbol_font: StyleFont::DejaVuSansMonoBold,
+            // 2.2 mm on common round drill
+            symbol_scale: 2.2 / 2.8,
+            symbol_baseline_offset: 0.25,
+            symbol_outline_min_contrast: None,
+            show_margins_frame: true,
+            show_occupied_area_frame: true,
+            frame_line_width_pt: 0.75,
+            margins_frame_color: [255, 0, 0],
+            occupied_area_frame_color: [0, 255, 0],
+            overlap_line_width_pt: 1.5,
+            overlap_line_color: [0, 0, 255],
+            grid_cell_line_width_pt: 0.2,
+            grid_block_line_width_pt: 0.9,
+            grid_line_color: [0, 0, 0],
+            map_line_width_pt: 1.0,
+            map_page_number_font_size_pt: 14.0,
+            map_tile_label_font_size_pt: 9.0,
+            footer_font_size_pt: 7.0,
+            ruler_font_size_pt: 5.0,
+            grid_ruler_size_mm: 4.0,
+            ruler_gap_mm: 0.8,
+            ruler_label_interval: 1,
+            legend_text_font_size_pt: 9.0,
+            legend_row_height_mm: 7.0,
+            legend_swatch_width_mm: 10.0,
+            legend_swatch_inset_mm: 1.0,
+            legend_symbol_column_mm: 12.0,
+            legend_code_column_mm: 24.0,
+            legend_name_column_mm: 50.0,
+            legend_drills_column_end_mm: 150.0,
+            legend_bags_column_end_mm: 180.0,
+            header_text: None,
+            footer_text: None,
+        }
+    }
+}
+
+impl RenderStyle {
+    /// TOML if file has `.toml` extension, JSON otherwise
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, RenderStyleError> {
+        load_toml_or_json(path)
+    }
+
+    /// Configured header band, or twice the header font height if larger
+    pub fn get_page_header_height(&self) -> mm {
+        mm::new(self.page_header_height_mm.max(2.0 * points_to_mm(self.header_font_size_pt)))
+    }
+
+    /// Configured ruler band, or gap plus three digits index if larger
+    pub fn get_grid_ruler_size(&self) -> mm {
+        let label_width_mm = RULER_LABEL_WIDTH_EM * points_to_mm(self.ruler_font_size_pt);
+        mm::new(self.grid_ruler_size_mm.max(self.ruler_gap_mm + label_width_mm))
+    }
+}
+
+/// Three digits cell index, digits are about 0.6 em wide
+const RULER_LABEL_WIDTH_EM: f32 = 1.8;
+
+fn points_to_mm(value: f32) -> f32 {
+    25.4 * value / 72.0
+}
+
+#[cfg(test)]
+mod test_style {
+    use super::{
+        RenderStyle,
+        StyleFont
+    };
+
+    #[test]
+    fn test_partial_json_style_uses_defaults() {
+        let style: RenderStyle = serde_json::from_str(r#"{
+            "header_font": "times_bold",
+            "show_margins_frame": false,
+            "footer_text": "Diamond Shop"
+        }"#).unwrap();
+
+        assert_eq!(style.header_font, StyleFont::DejaVuSerifBold);
+        assert!(!style.show_margins_frame);
+        assert_eq!(style.footer_text.as_deref(), Some("Diamond Shop"));
+        assert_eq!(style.grid_line_color, RenderStyle::default().grid_line_color);
+    }
+
+    #[test]
+    fn test_style_font_names() {
+        let style: RenderStyle = serde_json::from_str(r#"{
+            "header_font": "deja_vu_serif_bold",
+            "text_font": "helvetica",
+            "symbol_font": "courier_bold"
+        }"#).unwrap();
+
+        assert_eq!(style.header_font, StyleFont::DejaVuSerifBold);
+        assert_eq!(style.text_font, StyleFont::DejaVuSans);
+        assert_eq!(style.symbol_font, StyleFont::DejaVuSansMonoBold);
+        assert_eq!(serde_json::to_string(&StyleFont::DejaVuSansMono).unwrap(), r#""deja_vu_sans_mono""#);
+    }
+
+    #[test]
+    fn test_toml_style() {
+        let style: RenderStyle = toml::from_str(r#"
+            symbol_scale = 0.6
+            grid_line_color = [40, 40, 40]
+            header_text = "Diamond Shop"
+            legend_name_column_mm = 40.0
+        "#).unwrap();
+
+        assert_eq!(style.symbol_scale, 0.6);
+        assert_eq!(style.grid_line_color, [40, 40, 40]);
+        assert_eq!(style.legend_name_column_mm, 40.0);
+        assert_eq!(style.legend_row_height_mm, 7.0);
+        assert_eq!(style.header_text.as_deref(), Some("Diamond Shop"));
+        assert!(style.show_occupied_area_frame);
+    }
+
+    #[test]
+    fn test_bands_fit_configured_fonts() {
+        let default_style = RenderStyle::default();
+        assert_eq!(default_style.get_page_header_height().raw_value(), default_style.page_header_height_mm);
+        assert_eq!(default_style.get_grid_ruler_size().raw_value(), default_style.grid_ruler_size_mm);
+
+        let style = RenderStyle {
+            header_font_size_pt: 24.0,
+            ruler_font_size_pt: 9.0,
+            ..RenderStyle::default()
+        };
+        assert!(style.get_page_header_height().raw_value() > style.page_header_height_mm);
+        assert!(style.get_grid_ruler_size().raw_value() > style.grid_ruler_size_mm);
+    }
+
+    #[test]
+    fn test_load_style_file() {
+        let path = std::env::temp_dir().join("diamonds_imager_generator_style.toml");
+        std::fs::write(&path, "show_occupied_area_frame = false\n").unwrap();
+
+        let style = RenderStyle::load_from(&path);
+        assert!(style.is_ok());
+        assert!(!style.unwrap().show_occupied_area_frame);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    fmt::Write,
+    io,
+    path::{
+        Path,
+        PathBuf
+    }
+};
+
+use image::Rgb;
+
+use crate::backend::{
+    get_numbered_page_path,
+    GraphicsState,
+    PathShape,
+    PatternCanvas,
+    PatternDocument,
+    PatternFont
+};
+
+/// Every page is written to its own SVG file
+pub struct SvgDocument {
+    output_path: PathBuf,
+    pages_count: usize,
+}
+
+struct SvgCanvas {
+    page_height: f32,
+    state: GraphicsState,
+    content: String,
+}
+
+impl SvgDocument {
+    pub fn create<P: AsRef<Path>>(output_path: P) -> Self {
+        Self {
+            output_path: output_path.as_ref().to_path_buf(),
+            pages_count: 0
+        }
+    }
+}
+
+fn to_svg_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0[0], color.0[1], color.0[2])
+}
+
+fn escape_svg_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Font family and weight, DejaVu first with common look-alikes as fallback
+fn get_svg_font(font: PatternFont) -> (&'static str, &'static str) {
+    match font {
+        PatternFont::Sans => ("DejaVu Sans, Helvetica, Arial, sans-serif", "normal"),
+        PatternFont::SansBold => ("DejaVu Sans, Helvetica, Arial, sans-serif", "bold"),
+        PatternFont::Mono => ("DejaVu Sans Mono, Courier New, Courier, monospace", "normal"),
+        PatternFont::MonoBold => ("DejaVu Sans Mono, Courier New, Courier, monospace", "bold"),
+        PatternFont::Serif => ("DejaVu Serif, Times New Roman, Times, serif", "normal"),
+        PatternFont::SerifBold => ("DejaVu Serif, Times New Roman, Times, serif", "bold"),
+    }
+}
+
+impl SvgCanvas {
+    fn flip_y(&self, y: f32) -> f32 {
+        self.page_height - y
+    }
+
+    fn write_shape(&mut self, shape: PathShape, paint: &str) {
+        let _ = match shape {
+            PathShape::Rectangle { x, y, width, height } => writeln!(self.content,
+                r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" {paint}/>"#,
+                x, self.flip_y(y + height), width, height
+            ),
+            PathShape::Circle { x, y, radius } => writeln!(self.content,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" {paint}/>"#,
+                x, self.flip_y(y), radius
+            ),
+            PathShape::Line { x1, y1, x2, y2 } => writeln!(self.content,
+                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" {paint}/>"#,
+                x1, self.flip_y(y1), x2, self.flip_y(y2)
+            ),
+        };
+    }
+
+    fn write_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str, anchor: &str) -> io::Result<()> {
+        let (family, weight) = get_svg_font(font);
+        let _ = writeln!(self.content,
+            r#"<text x="{:.2}" y="{:.2}" font-family="{family}" font-weight="{weight}" font-size="{:.2}" text-anchor="{anchor}" fill="{}">{}</text>"#,
+            x, self.flip_y(y), size, to_svg_color(self.state.fill_color), escape_svg_text(text)
+        );
+        Ok(())
+    }
+}
+
+impl PatternCanvas for SvgCanvas {
+    fn set_fill_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        self.state.fill_color = color;
+        Ok(())
+    }
+
+    fn set_stroke_color(&mut self, color: Rgb<u8>) -> io::Result<()> {
+        self.state.stroke_color = color;
+        Ok(())
+    }
+
+    fn set_line_width(&mut self, width: f32) -> io::Result<()> {
+        self.state.line_width = width;
+        Ok(())
+    }
+
+    fn rectangle(&mut self, x: f32, y: f32, width: f32, height: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Rectangle { x, y, width, height });
+        Ok(())
+    }
+
+    fn circle(&mut self, x: f32, y: f32, radius: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Circle { x, y, radius });
+        Ok(())
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) -> io::Result<()> {
+        self.state.path.push(PathShape::Line { x1, y1, x2, y2 });
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let paint = format!(r#"fill="{}""#, to_svg_color(self.state.fill_color));
+        std::mem::take(&mut self.state.path).into_iter()
+            .filter(|shape| !matches!(shape, PathShape::Line { .. }))
+            .for_each(|shape| self.write_shape(shape, &paint));
+        Ok(())
+    }
+
+    fn stroke(&mut self) -> io::Result<()> {
+        let paint = format!(r#"fill="none" stroke="{}" stroke-width="{:.2}""#,
+            to_svg_color(self.state.stroke_color),
+            self.state.line_width
+        );
+        std::mem::take(&mut self.state.path).into_iter()
+            .for_each(|shape| self.write_shape(shape, &paint));
+        Ok(())
+    }
+
+    fn left_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        self.write_text(x, y, font, size, text, "start")
+    }
+
+    fn center_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        self.write_text(x, y, font, size, text, "middle")
+    }
+
+    fn right_text(&mut self, x: f32, y: f32, font: PatternFont, size: f32, text: &str) -> io::Result<()> {
+        self.write_text(x, y, font, size, text, "end")
+    }
+}
+
+impl PatternDocument for SvgDocument {
+    fn render_page<F>(&mut self, width: f32, height: f32, render_contents: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut dyn PatternCanvas) -> io::Result<()>
+    {
+        let mut canvas = SvgCanvas {
+            page_height: height,
+            state: GraphicsState::default(),
+            content: String::new()
+        };
+        render_contents(&mut canvas)?;
+
+        self.pages_count += 1;
+        let svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.2}pt\" height=\"{height:.2}pt\" viewBox=\"0 0 {width:.2} {height:.2}\">\n\
+            <rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n\
+            {}</svg>\n",
+            canvas.content
+        );
+        std::fs::write(get_numbered_page_path(&self.output_path, self.pages_count), svg)
+    }
+
+    fn finish(self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_svg_backend {
+    use image::Rgb;
+
+    use crate::backend::{
+        PatternDocument,
+        PatternFont
+    };
+
+    use super::SvgDocument;
+
+    #[test]
+    fn test_svg_page_content() {
+        let output_path = std::env::temp_dir().join("diamonds_imager_generator_test.svg");
+        let mut document = SvgDocument::create(&output_path);
+        let result = document.render_page(100.0, 50.0, |canvas| {
+            canvas.set_fill_color(Rgb([255, 0, 0]))?;
+            canvas.rectangle(10.0, 10.0, 20.0, 5.0)?;
+            canvas.fill()?;
+            canvas.set_fill_color(Rgb([0, 0, 0]))?;
+            canvas.center_text(50.0, 20.0, PatternFont::Sans, 8.0, "A<B")
+        });
+        assert!(result.is_ok());
+        assert!(document.finish().is_ok());
+
+        let svg = std::fs::read_to_string(std::env::temp_dir().join("diamonds_imager_generator_test_1.svg")).unwrap();
+        // Y axis flipped, rectangle top edge is 35pt from page top
+        assert!(svg.contains(r##"<rect x="10.00" y="35.00" width="20.00" height="5.00" fill="#ff0000"/>"##));
+        assert!(svg.contains("A&lt;B"));
+        assert!(svg.contains(r#"text-anchor="middle""#));
+    }
+}
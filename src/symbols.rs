@@ -0,0 +1,192 @@
+use crate::backend::PatternFont;
+
+/// Candidates in order of preference, latin ones drawn using style symbol font
+const LATIN_SYMBOLS: &[&str] = &[
+    "1", "2", "3", "4", "5", "6", "7", "8", "9", "0",
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M",
+    "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
+    "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m",
+    "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z",
+    "+", "=", "@", "#", "$", "%", "*", "&", "?", "<", ">", "~",
+];
+
+// Letters resembling latin ones are skipped by ambiguous groups
+const GREEK_SYMBOLS: &[&str] = &[
+    "α", "β", "γ", "δ", "ε", "ζ", "η", "θ", "λ", "μ", "ξ", "π", "σ", "φ", "ψ", "ω",
+    "Γ", "Δ", "Θ", "Λ", "Ξ", "Π", "Σ", "Φ", "Ψ", "Ω",
+];
+
+const DINGBATS_SYMBOLS: &[&str] = &[
+    "●", "■", "▲", "▼", "◆", "★", "♠", "♣", "♥", "♦",
+    "✚", "✖", "✱", "✿", "❀", "✈", "☎", "✂", "✓", "✗",
+    "❖", "✪", "✦", "➔", "❄", "✉", "✌", "✎", "✒", "❏",
+    "◗", "❋", "✤", "✩", "☛", "❂", "♪", "☀", "☺", "☂",
+    "⚓", "♛", "♞", "♜", "☯", "⌂", "⚑", "☁", "☽", "⚡",
+    "♀", "♂", "☢", "☮", "⌘", "♻", "☠", "♨",
+    "⚙", "☕", "♝", "⚔", "⚛", "☃", "☄", "⚕", "⚜", "∞",
+    "§", "¶", "⊕", "✍", "⚖", "⚗", "⌨", "⚱", "✆", "♫",
+    "⬢", "◉", "⌬", "⚘", "⚄", "✇", "√", "÷", "※",
+];
+
+/// Symbols easily mistaken for each other, only the first one found in candidates is used
+const AMBIGUOUS_GROUPS: &[&[&str]] = &[
+    &["0", "O", "o", "D", "Q", "σ", "θ", "Θ"],
+    &["A", "Λ"],
+    &["1", "l", "I", "i", "j", "J"],
+    &["2", "Z", "z"],
+    &["5", "S", "s", "§"],
+    &["6", "b", "G", "δ"],
+    &["8", "B", "β"],
+    &["9", "g", "q"],
+    &["a", "α"],
+    &["c", "C"],
+    &["k", "K"],
+    &["p", "P", "¶"],
+    &["u", "v", "U", "V", "μ"],
+    &["w", "W", "ω"],
+    &["x", "X", "✖", "✗"],
+    &["y", "Y", "γ"],
+    &["n", "h", "η"],
+    &["e", "ε"],
+    &["E", "Σ"],
+    &["F", "Γ", "r"],
+    &["ζ", "ξ"],
+    &["π", "Π"],
+    &["Δ", "▲"],
+    &["θ", "Θ"],
+    &["φ", "Φ"],
+    &["ψ", "Ψ"],
+    &["+", "✚"],
+    &["*", "✱", "❋"],
+    &["t", "f"],
+    &["◆", "♦", "❖"],
+    &["♠", "♣"],
+    &["●", "◉"],
+    &["⬢", "⌬"],
+    &["♪", "♫"],
+    &["✂", "⚔"],
+    &["✌", "✍"],
+    &["★", "✩", "✪", "✦", "❂"],
+    &["✿", "❀"],
+    &["✎", "✒"],
+];
+
+fn is_ambiguous_with_any(symbol: &str, picked: &[&str]) -> bool {
+    AMBIGUOUS_GROUPS.iter()
+        .filter(|group| group.contains(&symbol))
+        .any(|group| group.iter().any(|other| picked.contains(other)))
+}
+
+fn get_all_candidates() -> impl Iterator<Item = &'static str> {
+    LATIN_SYMBOLS.iter()
+        .chain(GREEK_SYMBOLS)
+        .chain(DINGBATS_SYMBOLS)
+        .copied()
+}
+
+/// Up to `count` symbols, none of them ambiguous with another
+pub fn generate_label_symbols(count: usize) -> Vec<&'static str> {
+    let mut picked = Vec::new();
+    for symbol in get_all_candidates() {
+        if picked.len() >= count {
+            break;
+        }
+        if !is_ambiguous_with_any(symbol, &picked) {
+            picked.push(symbol);
+        }
+    }
+    picked
+}
+
+pub fn get_label_symbols_max_count() -> usize {
+    generate_label_symbols(usize::MAX).len()
+}
+
+/// Font capable of drawing the symbol, `latin_font` is used for ASCII ones
+pub fn get_symbol_font(symbol: &str, latin_font: PatternFont) -> PatternFont {
+    if GREEK_SYMBOLS.contains(&symbol) || DINGBATS_SYMBOLS.contains(&symbol) {
+        PatternFont::SansBold
+    } else {
+        latin_font
+    }
+}
+
+#[cfg(test)]
+mod test_symbols {
+    use std::collections::HashSet;
+
+    use ab_glyph::{
+        Font,
+        FontRef
+    };
+
+    use crate::backend::PatternFont;
+
+    use super::{
+        generate_label_symbols,
+        get_label_symbols_max_count,
+        get_symbol_font,
+        AMBIGUOUS_GROUPS,
+        DINGBATS_SYMBOLS,
+        GREEK_SYMBOLS
+    };
+
+    #[test]
+    fn test_enough_symbols_for_large_palettes() {
+        // Headroom for new ambiguous groups
+        assert!(get_label_symbols_max_count() >= 110);
+
+        let symbols = generate_label_symbols(100);
+        assert_eq!(symbols.len(), 100);
+        assert_eq!(symbols.iter().collect::<HashSet<_>>().len(), 100);
+    }
+
+    #[test]
+    fn test_no_ambiguous_pairs() {
+        let symbols = generate_label_symbols(usize::MAX);
+        for (a, b) in [
+            ("1", "l"), ("1", "I"), ("0", "O"), ("5", "S"), ("8", "B"), ("x", "X"),
+            ("y", "Y"), ("π", "Π"), ("Δ", "▲"), ("o", "σ"), ("0", "σ"), ("E", "Σ"),
+            ("6", "δ"), ("F", "Γ"), ("r", "Γ"), ("F", "r"), ("0", "θ"), ("♠", "♣"),
+        ] {
+            assert!(!(symbols.contains(&a) && symbols.contains(&b)), "{a} and {b}");
+        }
+    }
+
+    #[test]
+    fn test_no_two_symbols_from_ambiguous_group() {
+        let symbols = generate_label_symbols(100);
+        assert_eq!(symbols.len(), 100);
+        for group in AMBIGUOUS_GROUPS {
+            let used = group.iter()
+                .filter(|symbol| symbols.contains(symbol))
+                .collect::<Vec<_>>();
+            assert!(used.len() <= 1, "{used:?}");
+        }
+    }
+
+    #[test]
+    fn test_symbols_prefix_stable() {
+        let small = generate_label_symbols(12);
+        let large = generate_label_symbols(60);
+        assert_eq!(small[..], large[..12]);
+    }
+
+    #[test]
+    fn test_symbol_fonts() {
+        let mono = PatternFont::MonoBold;
+        assert_eq!(get_symbol_font("A", mono), mono);
+        assert_eq!(get_symbol_font("A", PatternFont::Serif), PatternFont::Serif);
+        assert_eq!(get_symbol_font("λ", mono), PatternFont::SansBold);
+        assert_eq!(get_symbol_font("★", mono), PatternFont::SansBold);
+    }
+
+    #[test]
+    fn test_unicode_font_has_all_symbols() {
+        let font = FontRef::try_from_slice(PatternFont::SansBold.get_data()).unwrap();
+        for symbol in GREEK_SYMBOLS.iter().chain(DINGBATS_SYMBOLS) {
+            let c = symbol.chars().next().unwrap();
+            assert_ne!(font.glyph_id(c).0, 0, "{symbol}");
+        }
+    }
+}
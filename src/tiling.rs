@@ -0,0 +1,289 @@
+use crate::{
+    style::RenderStyle,
+    types::{
+        DiamondShape,
+        PaperSheet,
+        Pos2U,
+        Rect2D,
+        Rect2U,
+        Size2D,
+        Size2U
+    }
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub page_number: usize,
+    pub row: u32,
+    pub column: u32,
+    pub cells: Rect2U,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayout {
+    pub rows: u32,
+    pub columns: u32,
+    pub overlap_cells: u32,
+    pub tiles: Vec<Tile>,
+}
+
+/// Shrinks printing area, so rulers fit between grid and original margins
+pub fn reserve_grid_rulers_area(mut paper_sheet: PaperSheet, render_style: &RenderStyle) -> PaperSheet {
+    paper_sheet.print_margins.expand(render_style.get_grid_ruler_size());
+    paper_sheet
+}
+
+/// Printable area left for pattern cells below the page header
+pub fn get_tile_pattern_area_rect(paper_sheet: &PaperSheet, render_style: &RenderStyle) -> Rect2D {
+    let mut area_rect = paper_sheet.get_printing_area_rect();
+    area_rect.size.h -= render_style.get_page_header_height();
+    area_rect
+}
+
+/// Spreadsheet like label: A, B, ..., Z, AA, AB, ...
+pub fn get_row_label(row: u32) -> String {
+    let mut label = Vec::new();
+    let mut value = row + 1;
+    while value > 0 {
+        let rem = (value - 1) % 26;
+        label.push((b'A' + rem as u8) as char);
+        value = (value - 1) / 26;
+    }
+    label.iter().rev().collect()
+}
+
+/// Returns (start, length) of every tile along one axis
+fn split_axis(total_cells: u32, cells_per_page: u32, overlap_cells: u32) -> Vec<(u32, u32)> {
+    if total_cells <= cells_per_page {
+        return vec![(0, total_cells)];
+    }
+
+    let step = cells_per_page - overlap_cells;
+    let mut spans = Vec::new();
+    let mut start = 0;
+    loop {
+        let len = cells_per_page.min(total_cells - start);
+        spans.push((start, len));
+        if start + len >= total_cells {
+            break;
+        }
+        start += step;
+    }
+    spans
+}
+
+impl Tile {
+    pub fn get_title(&self, pages_count: usize) -> String {
+        format!("Page {} of {}, row {} col {}",
+            self.page_number,
+            pages_count,
+            get_row_label(self.row),
+            self.column + 1
+        )
+    }
+
+    /// Cells not shared with the tiles above and on the left
+    pub fn get_core_cells(&self, overlap_cells: u32) -> Rect2U {
+        let skip_x = if self.column > 0 { overlap_cells.min(self.cells.size.w) } else { 0 };
+        let skip_y = if self.row > 0 { overlap_cells.min(self.cells.size.h) } else { 0 };
+        Rect2U {
+            pos: Pos2U {
+                x: self.cells.pos.x + skip_x,
+                y: self.cells.pos.y + skip_y,
+            },
+            size: Size2U {
+                w: self.cells.size.w - skip_x,
+                h: self.cells.size.h - skip_y,
+            }
+        }
+    }
+}
+
+impl TileLayout {
+    pub fn compute(image_size: Size2U, cells_per_page: Size2U, overlap_cells: u32) -> Self {
+        let cells_per_page = Size2U {
+            w: cells_per_page.w.max(1),
+            h: cells_per_page.h.max(1),
+        };
+        // Overlap can't consume whole page
+        let overlap_cells = overlap_cells.min(cells_per_page.w.min(cells_per_page.h) - 1);
+
+        let columns_spans = split_axis(image_size.w, cells_per_page.w, overlap_cells);
+        let rows_spans = split_axis(image_size.h, cells_per_page.h, overlap_cells);
+
+        let tiles = rows_spans.iter()
+            .enumerate()
+            .flat_map(|(row, (y, h))| {
+                columns_spans.iter()
+                    .enumerate()
+                    .map(move |(column, (x, w))| (row, column, Rect2U {
+                        pos: Pos2U { x: *x, y: *y },
+                        size: Size2U { w: *w, h: *h }
+                    }))
+            })
+            .enumerate()
+            .map(|(idx, (row, column, cells))| Tile {
+                page_number: idx + 1,
+                row: row as u32,
+                column: column as u32,
+                cells
+            })
+            .collect();
+
+        Self {
+            rows: rows_spans.len() as u32,
+            columns: columns_spans.len() as u32,
+            overlap_cells,
+            tiles
+        }
+    }
+
+    pub fn for_paper_sheet(
+        paper_sheet: &PaperSheet,
+        diamond_shape: &DiamondShape,
+        render_style: &RenderStyle,
+        image_size: Size2U,
+        overlap_cells: u32
+    ) -> Self {
+        Self::compute(
+            image_size,
+            get_cells_per_page(paper_sheet, diamond_shape, render_style),
+            overlap_cells
+        )
+    }
+
+    pub fn pages_count(&self) -> usize {
+        self.tiles.len()
+    }
+}
+
+pub fn get_cells_per_page(paper_sheet: &PaperSheet, diamond_shape: &DiamondShape, render_style: &RenderStyle) -> Size2U {
+    let area_size: Size2D = get_tile_pattern_area_rect(paper_sheet, render_style).size;
+    Size2U {
+        w: (area_size.w / diamond_shape.get_size()).floor() as u32,
+        h: (area_size.h / diamond_shape.get_size()).floor() as u32,
+    }
+}
+
+/// Picks paper orientation resulting in less pages
+pub fn orient_paper_sheet_for_tiling(
+    mut paper_sheet: PaperSheet,
+    diamond_shape: &DiamondShape,
+    render_style: &RenderStyle,
+    image_size: Size2U,
+    overlap_cells: u32
+) -> PaperSheet {
+    let pages_count = TileLayout::for_paper_sheet(&paper_sheet, diamond_shape, render_style, image_size, overlap_cells).pages_count();
+
+    let mut rotated_paper_sheet = paper_sheet;
+    rotated_paper_sheet.change_orientation();
+    let rotated_pages_count = TileLayout::for_paper_sheet(&rotated_paper_sheet, diamond_shape, render_style, image_size, overlap_cells).pages_count();
+
+    if rotated_pages_count < pages_count {
+        paper_sheet = rotated_paper_sheet;
+    }
+    paper_sheet
+}
+
+#[cfg(test)]
+mod test_tiling {
+    use crate::{
+        style::RenderStyle,
+        types::{
+            DiamondShape,
+            PaperSheet,
+            Size2U
+        }
+    };
+
+    use super::{
+        get_cells_per_page,
+        get_row_label,
+        reserve_grid_rulers_area,
+        TileLayout
+    };
+
+    #[test]
+    fn test_row_labels() {
+        assert_eq!(get_row_label(0), "A");
+        assert_eq!(get_row_label(25), "Z");
+        assert_eq!(get_row_label(26), "AA");
+        assert_eq!(get_row_label(27), "AB");
+    }
+
+    #[test]
+    fn test_single_page_layout() {
+        let layout = TileLayout::compute(
+            Size2U { w: 50, h: 60 },
+            Size2U { w: 70, h: 100 },
+            2
+        );
+        assert_eq!(layout.pages_count(), 1);
+        assert_eq!(layout.tiles[0].cells.size, Size2U { w: 50, h: 60 });
+    }
+
+    #[test]
+    fn test_tiles_overlap_and_cover_image() {
+        let image_size = Size2U { w: 150, h: 220 };
+        let overlap_cells = 3;
+        let layout = TileLayout::compute(
+            image_size,
+            Size2U { w: 70, h: 100 },
+            overlap_cells
+        );
+        assert_eq!(layout.columns, 3);
+        assert_eq!(layout.rows, 3);
+        assert_eq!(layout.pages_count(), 9);
+
+        // Neighbours share overlap cells
+        assert_eq!(layout.tiles[0].cells.right() - layout.tiles[1].cells.pos.x, overlap_cells);
+
+        // Every cell is covered by exactly one core area
+        for y in 0..image_size.h {
+            for x in 0..image_size.w {
+                let covering = layout.tiles.iter()
+                    .filter(|tile| tile.get_core_cells(overlap_cells).contains(x, y))
+                    .count();
+                assert_eq!(covering, 1, "cell ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_title() {
+        let layout = TileLayout::compute(
+            Size2U { w: 200, h: 150 },
+            Size2U { w: 70, h: 100 },
+            2
+        );
+        assert_eq!(layout.pages_count(), 6);
+        assert_eq!(layout.tiles[4].get_title(layout.pages_count()), "Page 5 of 6, row B col 2");
+    }
+
+    #[test]
+    fn test_grid_rulers_reduce_cells_per_page() {
+        let paper_sheet = PaperSheet::standard_a4();
+        let diamond_shape = DiamondShape::common_round();
+        let render_style = RenderStyle::default();
+        let cells_per_page = get_cells_per_page(&paper_sheet, &diamond_shape, &render_style);
+        let cells_per_page_with_rulers = get_cells_per_page(&reserve_grid_rulers_area(paper_sheet, &render_style), &diamond_shape, &render_style);
+
+        assert!(cells_per_page_with_rulers.w < cells_per_page.w);
+        assert!(cells_per_page_with_rulers.h < cells_per_page.h);
+    }
+
+    #[test]
+    fn test_larger_header_font_reduces_cells_per_page() {
+        let paper_sheet = PaperSheet::standard_a4();
+        let diamond_shape = DiamondShape::common_round();
+        let render_style = RenderStyle {
+            header_font_size_pt: 30.0,
+            ..RenderStyle::default()
+        };
+
+        let cells_per_page = get_cells_per_page(&paper_sheet, &diamond_shape, &RenderStyle::default());
+        let cells_per_page_large_header = get_cells_per_page(&paper_sheet, &diamond_shape, &render_style);
+        assert_eq!(cells_per_page_large_header.w, cells_per_page.w);
+        assert!(cells_per_page_large_header.h < cells_per_page.h);
+    }
+}
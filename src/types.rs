@@ -3,8 +3,58 @@ use millimeter::{
     mm, 
     Unit
 };
+use serde::{
+    Deserialize, 
+    Serialize
+};
 
-#[derive(Debug, Clone, Copy)]
+/// Millimeters stored as plain numbers in project files
+pub(crate) mod serde_mm {
+    use millimeter::mm;
+    use serde::{
+        Deserialize, 
+        Deserializer, 
+        Serialize, 
+        Serializer
+    };
+
+    pub fn serialize<S: Serializer>(value: &mm, serializer: S) -> Result<S::Ok, S::Error> {
+        value.raw_value().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<mm, D::Error> {
+        f32::deserialize(deserializer).map(mm::new)
+    }
+}
+
+/// `Size2D` stored as plain numbers of millimeters
+pub(crate) mod serde_size_mm {
+    use millimeter::mm;
+    use serde::{
+        Deserialize, 
+        Deserializer, 
+        Serialize, 
+        Serializer
+    };
+
+    use super::{
+        Size2D, 
+        Size2F
+    };
+
+    pub fn serialize<S: Serializer>(value: &Size2D, serializer: S) -> Result<S::Ok, S::Error> {
+        Size2F::from(value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Size2D, D::Error> {
+        Size2F::deserialize(deserializer).map(|size| Size2D {
+            w: mm::new(size.w),
+            h: mm::new(size.h)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Size2X<T> 
 {
     pub w: T,
@@ -69,16 +119,38 @@ pub struct Pos2D {
     pub y: mm,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pos2U {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Rectangle in cells, y axis pointing down like in image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect2U {
+    pub pos: Pos2U,
+    pub size: Size2U,
+}
+
+/// Sizes in mm
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct PaperSheet {
+    #[serde(with = "serde_size_mm")]
     pub size: Size2D,
-    pub print_margins: MarginsMirrored2D
+    /// Per side since 0.2.0, replaced mirrored `MarginsMirrored2D`
+    pub print_margins: Margins2D
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct MarginsMirrored2D {
-    pub vertical: mm,
-    pub horizontal: mm,
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Margins2D {
+    #[serde(with = "serde_mm")]
+    pub top: mm,
+    #[serde(with = "serde_mm")]
+    pub right: mm,
+    #[serde(with = "serde_mm")]
+    pub bottom: mm,
+    #[serde(with = "serde_mm")]
+    pub left: mm,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -87,12 +159,16 @@ pub struct Rect2D {
     pub size: Size2D,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Sizes in mm
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
 pub enum DiamondShape {
     Round {
+        #[serde(with = "serde_mm")]
         diameter: mm
     },
     Square {
+        #[serde(with = "serde_mm")]
         side: mm
     }
 }
@@ -128,9 +204,45 @@ impl From<Rect2D> for (mm, mm, mm, mm) {
     }
 }
 
-impl MarginsMirrored2D {
-    pub fn swap_v_h(&mut self) {
-        std::mem::swap(&mut self.vertical, &mut self.horizontal);
+impl Margins2D {
+    pub fn uniform(margin: mm) -> Self {
+        Self {
+            top: margin,
+            right: margin,
+            bottom: margin,
+            left: margin
+        }
+    }
+
+    /// Follows paper turned by quarter counterclockwise, right side becomes top
+    pub fn rotate(&mut self) {
+        *self = Self {
+            top: self.right,
+            right: self.bottom,
+            bottom: self.left,
+            left: self.top
+        };
+    }
+
+    pub fn expand(&mut self, by: mm) {
+        self.top += by;
+        self.right += by;
+        self.bottom += by;
+        self.left += by;
+    }
+}
+
+impl Rect2U {
+    pub fn right(&self) -> u32 {
+        self.pos.x + self.size.w
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.pos.y + self.size.h
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.pos.x && x < self.right() && y >= self.pos.y && y < self.bottom()
     }
 }
 
@@ -184,18 +296,18 @@ impl DiamondShape {
 impl PaperSheet {
     pub fn change_orientation(&mut self) {
         self.size.swap_w_h();
-        self.print_margins.swap_v_h();
+        self.print_margins.rotate();
     }
 
     pub fn get_printing_area_rect(&self) -> Rect2D {
         Rect2D {
             pos: Pos2D { 
-                x: self.print_margins.horizontal, 
-                y: self.print_margins.vertical,
+                x: self.print_margins.left, 
+                y: self.print_margins.bottom,
             },
             size: Size2D {
-                w: self.size.w - 2.0 * self.print_margins.horizontal,
-                h: self.size.h - 2.0 * self.print_margins.vertical,
+                w: self.size.w - self.print_margins.left - self.print_margins.right,
+                h: self.size.h - self.print_margins.top - self.print_margins.bottom,
             }
         }
     }
@@ -206,10 +318,7 @@ impl PaperSheet {
                 w: 210.0.mm(),
                 h: 297.0.mm()
             },
-            print_margins: MarginsMirrored2D {
-                vertical: 6.0.mm(),
-                horizontal: 6.0.mm()
-            }
+            print_margins: Margins2D::uniform(6.0.mm())
         }
     }
 
@@ -219,11 +328,7 @@ impl PaperSheet {
                 w: 297.0.mm(),
                 h: 420.0.mm()
             },
-            print_margins: MarginsMirrored2D {
-                vertical: 8.0.mm(),
-                horizontal: 8.0.mm()
-            }
+            print_margins: Margins2D::uniform(8.0.mm())
         }
     }
 }
-